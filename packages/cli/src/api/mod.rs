@@ -0,0 +1,3 @@
+mod ms;
+
+pub use ms::*;
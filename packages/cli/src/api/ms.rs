@@ -0,0 +1,350 @@
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// The Borrow API used when no endpoint is configured.
+#[cfg(not(debug_assertions))]
+pub static DEFAULT_ENDPOINT: &str = "https://api.borrow.dev";
+#[cfg(debug_assertions)]
+pub static DEFAULT_ENDPOINT: &str = "http://localhost:8787";
+
+/// The request whose latency is measured, `measureRequest` in the ms API.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MeasureRequest {
+    pub url: String,
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+/// A header the response must have, with a text its value must contain.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HeaderAssertion {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+/// A JSON path of the response body, e.g. `$.ok`, that must exist or compare to a value.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JsonPathAssertion {
+    pub path: String,
+    /// "==" or "!=", the path only has to exist when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<serde_json::Value>,
+}
+
+/// Checks every response must pass to count as successful, instead of any 2xx status.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Assertions {
+    /// Accepted response statuses, any 2xx status when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub status: Vec<u16>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<HeaderAssertion>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub body_contains: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub json_path: Vec<JsonPathAssertion>,
+}
+
+/// Parameters of a `measure` call to the ms API.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "action", rename = "measure", rename_all = "camelCase")]
+pub struct MeasureParams {
+    pub measure_request: MeasureRequest,
+    /// Region codes to measure from, every region when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regions: Option<Vec<String>>,
+    /// Requests made from each region, the host's default when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iterations: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_video: Option<bool>,
+    /// Parts of the request to publish with the results (body, headers, query).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub publish: Vec<String>,
+    /// "public" or "private".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assertions: Option<Assertions>,
+}
+
+impl MeasureParams {
+    pub fn new(measure_request: MeasureRequest) -> Self {
+        Self {
+            measure_request,
+            regions: None,
+            iterations: None,
+            enable_video: None,
+            publish: Vec::new(),
+            scope: None,
+            assertions: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RegionMetadata {
+    pub country: String,
+    pub region: String,
+    pub direction: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStats {
+    /// The amount of successful requests made to the API
+    pub amount: u64,
+    /// The amount of requests that were attempted, not reported by older hosts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iterations: Option<u64>,
+    /// The amount of responses that failed the assertions, only reported when there are any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assertion_failures: Option<u64>,
+    /// Why the first of these responses failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_assertion_failure: Option<String>,
+    /// The 50th percentile latency in milliseconds
+    pub p50: f64,
+    /// The 90th percentile latency in milliseconds
+    pub p90: f64,
+    /// The 99th percentile latency in milliseconds
+    pub p99: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RegionLatency {
+    pub metadata: RegionMetadata,
+    pub data: LatencyStats,
+}
+
+/// A successful `measure` response, mirroring `msResultSuccessSchema`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "result", rename = "success", rename_all = "camelCase")]
+pub struct MeasureResponse {
+    pub status: u16,
+    /// Latency by region code.
+    pub latency: BTreeMap<String, RegionLatency>,
+    /// Link to the published results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub video_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+    message: String,
+}
+
+#[derive(Debug)]
+pub enum MsError {
+    /// The parameters were rejected (`INVALID_PARAMS`).
+    InvalidParams(String),
+    /// The API key is missing or invalid (`UNAUTHORIZED`).
+    Unauthorized(String),
+    /// No region could reach the measured API (`UPSTREAM`).
+    Upstream(String),
+    /// The measured API answered every request with an error status that retrying won't fix,
+    /// e.g. a 404 or 501, when measuring from this machine (`UPSTREAM`).
+    UpstreamStatus { status: u16, message: String },
+    /// An error code this client doesn't know about.
+    Other { code: String, message: String },
+    /// The ms API answered with an unexpected HTTP status.
+    Http { status: u16, body: String },
+    /// The ms API couldn't be reached.
+    Request(reqwest::Error),
+    /// The measurement didn't finish in time.
+    Timeout(Duration),
+    /// The response doesn't match the expected schema.
+    InvalidResponse(String),
+}
+
+impl MsError {
+    fn from_code(code: String, message: String) -> Self {
+        match code.as_str() {
+            "INVALID_PARAMS" => Self::InvalidParams(message),
+            "UNAUTHORIZED" => Self::Unauthorized(message),
+            "UPSTREAM" => Self::Upstream(message),
+            _ => Self::Other { code, message },
+        }
+    }
+
+    /// The ms API error code, if the error comes from the API itself.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Self::InvalidParams(_) => Some("INVALID_PARAMS"),
+            Self::Unauthorized(_) => Some("UNAUTHORIZED"),
+            Self::Upstream(_) | Self::UpstreamStatus { .. } => Some("UPSTREAM"),
+            Self::Other { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+
+    /// Whether the error is likely transient, so that the same request may succeed when retried:
+    /// connection failures, `UPSTREAM` errors of the ms API, and 429, 502, 503 and 504 responses.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Upstream(_) => true,
+            Self::Http { status, .. } => matches!(status, 429 | 502..=504),
+            Self::Request(e) => e.is_connect() || e.is_request() || e.is_timeout(),
+            _ => false,
+        }
+    }
+
+    /// Whether the same request can never succeed, because the ms API rejected the request
+    /// (`INVALID_PARAMS`) or the API key (`UNAUTHORIZED`).
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Self::InvalidParams(_) | Self::Unauthorized(_))
+    }
+}
+
+impl fmt::Display for MsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidParams(message)
+            | Self::Unauthorized(message)
+            | Self::Upstream(message)
+            | Self::UpstreamStatus { message, .. }
+            | Self::Other { message, .. } => {
+                write!(f, "[{}] {message}", self.code().unwrap_or_default())
+            }
+            Self::Http { status, body } => write!(f, "unexpected response ({status}): {body}"),
+            Self::Request(e) => write!(f, "request failed: {e}"),
+            Self::Timeout(timeout) => write!(
+                f,
+                "timed out after {}",
+                humantime::format_duration(*timeout)
+            ),
+            Self::InvalidResponse(e) => write!(f, "failed to parse response: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for MsError {}
+
+/// How long a measurement may take when no timeout is given.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_mins(3);
+
+/// Client for the ms API.
+#[derive(Debug, Clone)]
+pub struct MsClient {
+    pub api_key: String,
+    pub timeout: Duration,
+    /// Base URL of the Borrow API, e.g. a self-hosted ms host.
+    pub endpoint: String,
+}
+
+impl MsClient {
+    pub fn new(api_key: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+        }
+    }
+
+    /// Measures the latency of a request from the regions of the ms API.
+    pub fn measure(&self, params: &MeasureParams) -> Result<MeasureResponse, MsError> {
+        let client = reqwest::blocking::Client::new();
+        let response = client
+            .post(format!("{}/v1/ms", self.endpoint.trim_end_matches('/')))
+            .header("X-Borrow-Api-Key", &self.api_key)
+            .timeout(self.timeout)
+            .json(params)
+            .send()
+            .map_err(|e| self.request_error(e))?;
+
+        let status = response.status();
+        let body = response.text().map_err(|e| self.request_error(e))?;
+
+        // Errors are reported in the body, with or without an error status.
+        let parsed: serde_json::Value = match serde_json::from_str(&body) {
+            Ok(parsed) => parsed,
+            Err(_) if !status.is_success() => {
+                return Err(MsError::Http {
+                    status: status.as_u16(),
+                    body,
+                });
+            }
+            Err(e) => return Err(MsError::InvalidResponse(format!("{e}: {body}"))),
+        };
+
+        if parsed["result"] == "error" {
+            let error: ErrorResponse = serde_json::from_value(parsed)
+                .map_err(|e| MsError::InvalidResponse(format!("{e}: {body}")))?;
+            return Err(MsError::from_code(error.error, error.message));
+        }
+        if !status.is_success() {
+            return Err(MsError::Http {
+                status: status.as_u16(),
+                body,
+            });
+        }
+
+        serde_json::from_value(parsed).map_err(|e| MsError::InvalidResponse(format!("{e}: {body}")))
+    }
+
+    /// Where the video of a published measurement is downloaded from, by its ID.
+    pub fn video_url(&self, id: &str) -> String {
+        format!("{}/v1/ms/{id}/video", self.endpoint.trim_end_matches('/'))
+    }
+
+    /// Downloads a measurement video, from its `videoUrl` or [`MsClient::video_url`].
+    pub fn fetch_video(&self, url: &str) -> Result<Vec<u8>, MsError> {
+        let client = reqwest::blocking::Client::new();
+        let mut request = client.get(url).timeout(self.timeout);
+        // The API key is only sent to the Borrow API, not to where videos are hosted.
+        if url.starts_with(&format!("{}/", self.endpoint.trim_end_matches('/'))) {
+            request = request.header("X-Borrow-Api-Key", &self.api_key);
+        }
+        let response = request.send().map_err(|e| self.request_error(e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().map_err(|e| self.request_error(e))?;
+            return Err(match serde_json::from_str::<ErrorResponse>(&body) {
+                Ok(error) => MsError::from_code(error.error, error.message),
+                Err(_) => MsError::Http {
+                    status: status.as_u16(),
+                    body,
+                },
+            });
+        }
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        if !content_type.starts_with("video/") {
+            return Err(MsError::InvalidResponse(format!(
+                "expected a video from {url}, got '{content_type}'"
+            )));
+        }
+        let video = response.bytes().map_err(|e| self.request_error(e))?;
+        Ok(video.to_vec())
+    }
+
+    fn request_error(&self, error: reqwest::Error) -> MsError {
+        if error.is_timeout() {
+            MsError::Timeout(self.timeout)
+        } else {
+            MsError::Request(error)
+        }
+    }
+}
+
+/// Measures the latency of a request from the regions of the ms API, with the default timeout.
+pub fn measure(api_key: &str, params: &MeasureParams) -> Result<MeasureResponse, MsError> {
+    MsClient::new(api_key).measure(params)
+}
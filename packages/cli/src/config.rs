@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+/// Settings of the `ms` commands.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct MsConfig {
+    /// Base URL of the Borrow API.
+    pub endpoint: Option<String>,
+}
+
+/// The user's settings, read from `config.toml` in the Borrow config directory.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct Config {
+    #[serde(default)]
+    pub ms: MsConfig,
+}
+
+pub fn get_root_config_dir() -> PathBuf {
+    match dirs::config_dir() {
+        Some(path) => path.join("borrow"),
+        None => PathBuf::from("./.borrow"),
+    }
+}
+
+pub fn get_config_path() -> PathBuf {
+    get_root_config_dir().join("config.toml")
+}
+
+/// Reads the config file, defaults are used when it doesn't exist.
+pub fn load_config() -> Result<Config, String> {
+    let path = get_config_path();
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(format!("failed to read '{}': {e}", path.display())),
+    };
+    toml::from_str(&content).map_err(|e| format!("invalid config file '{}': {e}", path.display()))
+}
@@ -1,7 +1,13 @@
+mod api;
+mod config;
+
 use std::path::PathBuf;
 
 use dirs::data_dir;
 
+pub use api::*;
+pub use config::*;
+
 pub fn get_root_data_dir() -> PathBuf {
     let data_dir = data_dir();
     match data_dir {
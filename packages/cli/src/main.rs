@@ -19,13 +19,13 @@ enum Command {
 
     /// Measure the latency of your API around the world
     #[command(name = "ms")]
-    Ms(MsCommand),
+    Ms(Box<MsCommand>),
 }
 
 fn main() {
     let cli = Cli::parse();
     match cli.command {
         Command::StartCommand(start_command) => handle_start_command(start_command),
-        Command::Ms(ms_command) => handle_ms_command(ms_command),
+        Command::Ms(ms_command) => handle_ms_command(*ms_command),
     }
 }
@@ -0,0 +1,271 @@
+use borrow_dev::{Assertions, HeaderAssertion, JsonPathAssertion};
+use clap::Args;
+use serde_json::Value;
+
+use super::RegionRow;
+
+#[derive(Args, Debug, Default, Clone)]
+pub struct AssertionArgs {
+    /// Count only responses with this status as successful, instead of any 2xx (repeatable)
+    #[arg(long = "expect-status", value_parser = clap::value_parser!(u16).range(100..=599))]
+    pub status: Vec<u16>,
+
+    /// Header the responses must have, "Name" or "Name: text" to check its value contains text (repeatable)
+    #[arg(long = "expect-header", value_name = "HEADER", value_parser = parse_header_assertion)]
+    pub headers: Vec<HeaderAssertion>,
+
+    /// Text the response bodies must contain (repeatable)
+    #[arg(long = "expect-body-contains", value_name = "TEXT")]
+    pub body_contains: Vec<String>,
+
+    /// JSON path of the response bodies that must exist, or compare to a value: "$.ok == true" (repeatable)
+    #[arg(long = "expect-json-path", value_name = "PATH", value_parser = parse_json_path_assertion)]
+    pub json_path: Vec<JsonPathAssertion>,
+}
+
+impl AssertionArgs {
+    pub fn is_empty(&self) -> bool {
+        self.status.is_empty()
+            && self.headers.is_empty()
+            && self.body_contains.is_empty()
+            && self.json_path.is_empty()
+    }
+
+    /// The assertions of the `measure` payload, `None` when there are none.
+    pub fn to_assertions(&self) -> Option<Assertions> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(Assertions {
+            status: self.status.clone(),
+            headers: self.headers.clone(),
+            body_contains: self.body_contains.clone(),
+            json_path: self.json_path.clone(),
+        })
+    }
+}
+
+pub fn parse_header_assertion(value: &str) -> Result<HeaderAssertion, String> {
+    let (name, text) = match value.split_once(':') {
+        Some((name, text)) => (name.trim(), Some(text.trim().to_string())),
+        None => (value.trim(), None),
+    };
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(format!(
+            "invalid header assertion '{value}', expected 'Name' or 'Name: text'"
+        ));
+    }
+    Ok(HeaderAssertion {
+        name: name.to_string(),
+        value: text,
+    })
+}
+
+/// Whether `path` is a JSON path the ms workers can read: `$` followed by `.key`, `[0]`, `["key"]`
+/// or `['key']` segments.
+fn is_json_path(path: &str) -> bool {
+    let Some(mut rest) = path.strip_prefix('$') else {
+        return false;
+    };
+    while !rest.is_empty() {
+        let end = if let Some(key) = rest.strip_prefix('.') {
+            let mut chars = key.chars();
+            if !chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+            {
+                return false;
+            }
+            1 + key
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(key.len())
+        } else if let Some(index) = rest.strip_prefix('[') {
+            match index.chars().next() {
+                Some(quote @ ('"' | '\'')) => match index[1..].find(quote) {
+                    Some(i) if index[i + 2..].starts_with(']') => i + 4,
+                    _ => return false,
+                },
+                _ => match index.find(']') {
+                    Some(i) if i > 0 && index[..i].bytes().all(|b| b.is_ascii_digit()) => i + 2,
+                    _ => return false,
+                },
+            }
+        } else {
+            return false;
+        };
+        rest = &rest[end..];
+    }
+    true
+}
+
+/// Finds the first `==` or `!=` that isn't inside a quoted key of the path.
+fn find_operator(value: &str) -> Option<(usize, &'static str)> {
+    let mut quote = None;
+    for (i, c) in value.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None => {
+                let rest = &value[i..];
+                if let Some(op) = ["==", "!="].into_iter().find(|op| rest.starts_with(op)) {
+                    return Some((i, op));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Parses "$.path", "$.path == value" or "$.path != value". The value is a JSON string, number,
+/// boolean or null, and any other text is compared as a string.
+pub fn parse_json_path_assertion(value: &str) -> Result<JsonPathAssertion, String> {
+    let (path, operator, expected) = match find_operator(value) {
+        Some((i, op)) => {
+            let expected = value[i + op.len()..].trim();
+            if expected.is_empty() {
+                return Err(format!("missing value after '{op}' in '{value}'"));
+            }
+            let expected = match serde_json::from_str::<Value>(expected) {
+                Ok(Value::Array(_) | Value::Object(_)) => {
+                    return Err(format!(
+                        "invalid value '{expected}', expected a string, number, boolean or null"
+                    ));
+                }
+                Ok(json) => json,
+                Err(_) => Value::String(expected.to_string()),
+            };
+            (value[..i].trim(), Some(op.to_string()), Some(expected))
+        }
+        None => (value.trim(), None, None),
+    };
+
+    if !is_json_path(path) {
+        return Err(format!(
+            "invalid JSON path '{path}', expected a path such as $.data.items[0].id"
+        ));
+    }
+    Ok(JsonPathAssertion {
+        path: path.to_string(),
+        operator,
+        value: expected,
+    })
+}
+
+/// Exits when assertions are given to a command that doesn't check them, e.g. `watch`.
+pub fn reject_assertions(args: &AssertionArgs, command: &str) {
+    if !args.is_empty() {
+        eprintln!(
+            "Error: `borrow ms {command}` doesn't check response assertions, use them with `borrow ms`, `compare` or `batch`."
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Prints the outcome of the assertions to stderr, returning whether any response failed them.
+pub fn check_assertions(regions: &[RegionRow]) -> bool {
+    if regions.iter().all(|r| r.assertion_failures.is_none()) {
+        return false;
+    }
+
+    let failed: Vec<&RegionRow> = regions
+        .iter()
+        .filter(|r| r.assertion_failures.is_some_and(|f| f > 0))
+        .collect();
+    if failed.is_empty() {
+        eprintln!("✅ All responses passed the assertions.");
+        return false;
+    }
+
+    eprintln!(
+        "❌ Responses failed the assertions in {} region(s):",
+        failed.len()
+    );
+    for region in failed {
+        eprintln!(
+            "  {} ({}, {}): {} failed, first: {}",
+            region.code,
+            region.country,
+            region.region,
+            region.assertion_failures_label(),
+            region.first_assertion_failure.as_deref().unwrap_or("-")
+        );
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_header_assertions() {
+        let header = parse_header_assertion("Content-Type: application/json").unwrap();
+        assert_eq!(header.name, "Content-Type");
+        assert_eq!(header.value.as_deref(), Some("application/json"));
+
+        let header = parse_header_assertion(" X-Request-Id ").unwrap();
+        assert_eq!(header.name, "X-Request-Id");
+        assert_eq!(header.value, None);
+
+        assert!(parse_header_assertion("").is_err());
+        assert!(parse_header_assertion("Content Type: json").is_err());
+    }
+
+    #[test]
+    fn parses_json_paths_and_values() {
+        let exists = parse_json_path_assertion(" $.data.items[0].id ").unwrap();
+        assert_eq!(exists.path, "$.data.items[0].id");
+        assert_eq!(exists.operator, None);
+        assert_eq!(exists.value, None);
+
+        let equal = parse_json_path_assertion("$.ok == true").unwrap();
+        assert_eq!(equal.path, "$.ok");
+        assert_eq!(equal.operator.as_deref(), Some("=="));
+        assert_eq!(equal.value, Some(Value::Bool(true)));
+
+        let different = parse_json_path_assertion("$.count!=3").unwrap();
+        assert_eq!(different.operator.as_deref(), Some("!="));
+        assert_eq!(different.value, Some(serde_json::json!(3)));
+
+        // Text that isn't JSON is compared as a string.
+        let text = parse_json_path_assertion("$.status == ok").unwrap();
+        assert_eq!(text.value, Some(Value::String("ok".to_string())));
+        let quoted = parse_json_path_assertion(r#"$.status == "a == b""#).unwrap();
+        assert_eq!(quoted.value, Some(Value::String("a == b".to_string())));
+    }
+
+    #[test]
+    fn parses_quoted_keys_with_spaces_and_operators() {
+        let spaced = parse_json_path_assertion(r#"$["a b"] == 1"#).unwrap();
+        assert_eq!(spaced.path, r#"$["a b"]"#);
+        assert_eq!(spaced.value, Some(serde_json::json!(1)));
+
+        let operator = parse_json_path_assertion("$['x == y'].z").unwrap();
+        assert_eq!(operator.path, "$['x == y'].z");
+        assert_eq!(operator.operator, None);
+
+        let header = parse_json_path_assertion(r#"$.headers["content-type"][0]"#).unwrap();
+        assert_eq!(header.path, r#"$.headers["content-type"][0]"#);
+    }
+
+    #[test]
+    fn rejects_invalid_json_paths_and_values() {
+        for path in [
+            "data.id",
+            "$.a b",
+            "$.",
+            "$.0",
+            "$[x]",
+            "$[]",
+            r#"$["a b"#,
+            "$.items[*]",
+            "$ .a",
+        ] {
+            assert!(parse_json_path_assertion(path).is_err(), "{path}");
+        }
+        assert!(parse_json_path_assertion("$.a ==").is_err());
+        assert!(parse_json_path_assertion("$.a == [1]").is_err());
+        assert!(parse_json_path_assertion(r#"$.a == {"b": 1}"#).is_err());
+    }
+}
@@ -0,0 +1,380 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use clap::Args;
+
+use super::{
+    MsReport, RegionRow, delta_percent, load_latest_measurement, parse_percent, report_to_json,
+};
+
+/// Changes smaller than this are noise, whatever the tolerance.
+const MIN_SIGNIFICANT_CHANGE_MS: f64 = 5.0;
+
+/// Below this many successful requests, p99 is about the slowest request and too noisy to compare.
+const MIN_P99_SAMPLES: u64 = 20;
+
+#[derive(Args, Debug, Clone)]
+pub struct BaselineArgs {
+    /// Save the results to this file, as a baseline to compare later runs with
+    #[arg(long = "save-baseline")]
+    pub save_baseline: Option<PathBuf>,
+
+    /// Compare the results with a saved baseline (or any `-o json` output) and fail on regressions
+    #[arg(long = "baseline")]
+    pub baseline: Option<PathBuf>,
+
+    /// How much slower a percentile may get before it counts as a regression (e.g. 15%)
+    #[arg(long = "tolerance", value_parser = parse_percent, default_value = "10%", requires = "baseline")]
+    pub tolerance: f64,
+}
+
+/// Per-region results a run is compared with.
+pub struct Baseline {
+    /// Describes where the baseline comes from, e.g. the file it was read from.
+    pub label: String,
+    pub method: Option<String>,
+    pub url: Option<String>,
+    pub regions: Vec<RegionRow>,
+}
+
+/// Reads the most recent successful measurement of a baseline file.
+pub fn load_baseline(path: &Path) -> Result<Baseline, String> {
+    let (saved, results) = load_latest_measurement(path)?;
+    let label = match saved.timestamp {
+        Some(timestamp) => format!(
+            "{} ({})",
+            path.display(),
+            timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+        ),
+        None => path.display().to_string(),
+    };
+    Ok(Baseline {
+        label,
+        method: saved.method,
+        url: saved.url,
+        regions: results.regions,
+    })
+}
+
+/// Writes the report as a baseline, the `-o json` document with the time it was measured.
+pub fn save_baseline(path: &Path, report: &MsReport) -> Result<(), String> {
+    let mut baseline = report_to_json(report);
+    baseline["timestamp"] = serde_json::json!(Utc::now().to_rfc3339());
+    let content = serde_json::to_string_pretty(&baseline).unwrap() + "\n";
+    std::fs::write(path, content).map_err(|e| format!("failed to write '{}': {e}", path.display()))
+}
+
+#[derive(Debug, Clone)]
+pub struct PercentileChange {
+    pub percentile: &'static str,
+    pub before: f64,
+    pub after: f64,
+}
+
+#[derive(Debug, Clone)]
+pub enum BaselineOutcome {
+    /// At least one percentile is slower beyond the tolerance.
+    Regressed(Vec<PercentileChange>),
+    /// At least one percentile is faster beyond the tolerance, and none is slower.
+    Improved(Vec<PercentileChange>),
+    Unchanged,
+    /// Measured in the baseline but not in this run.
+    Missing,
+    /// Measured in this run but not in the baseline.
+    New,
+}
+
+#[derive(Debug, Clone)]
+pub struct RegionComparison {
+    pub region: RegionRow,
+    pub outcome: BaselineOutcome,
+}
+
+/// Whether the change is beyond both the tolerance (in percent) and the noise floor.
+fn is_significant(before: f64, after: f64, tolerance: f64) -> bool {
+    let delta = (after - before).abs();
+    if delta <= MIN_SIGNIFICANT_CHANGE_MS {
+        return false;
+    }
+    before == 0.0 || delta / before * 100.0 > tolerance
+}
+
+fn compare_region(before: &RegionRow, after: &RegionRow, tolerance: f64) -> BaselineOutcome {
+    let mut checks = vec![
+        ("p50", before.p50, after.p50),
+        ("p90", before.p90, after.p90),
+    ];
+    if before.amount >= MIN_P99_SAMPLES && after.amount >= MIN_P99_SAMPLES {
+        checks.push(("p99", before.p99, after.p99));
+    }
+
+    let (regressed, improved): (Vec<_>, Vec<_>) = checks
+        .into_iter()
+        .filter(|(_, before, after)| is_significant(*before, *after, tolerance))
+        .map(|(percentile, before, after)| PercentileChange {
+            percentile,
+            before,
+            after,
+        })
+        .partition(|change| change.after > change.before);
+
+    if !regressed.is_empty() {
+        BaselineOutcome::Regressed(regressed)
+    } else if !improved.is_empty() {
+        BaselineOutcome::Improved(improved)
+    } else {
+        BaselineOutcome::Unchanged
+    }
+}
+
+/// Compares every region of the run and of the baseline, in the order of the run.
+pub fn compare_with_baseline(
+    baseline: &[RegionRow],
+    regions: &[RegionRow],
+    tolerance: f64,
+) -> Vec<RegionComparison> {
+    let mut comparisons: Vec<RegionComparison> = regions
+        .iter()
+        .map(|region| {
+            let outcome = match baseline.iter().find(|b| b.code == region.code) {
+                Some(before) => compare_region(before, region, tolerance),
+                None => BaselineOutcome::New,
+            };
+            RegionComparison {
+                region: region.clone(),
+                outcome,
+            }
+        })
+        .collect();
+    comparisons.extend(
+        baseline
+            .iter()
+            .filter(|b| !regions.iter().any(|r| r.code == b.code))
+            .map(|b| RegionComparison {
+                region: b.clone(),
+                outcome: BaselineOutcome::Missing,
+            }),
+    );
+    comparisons
+}
+
+fn print_changes(icon: &str, region: &RegionRow, changes: &[PercentileChange]) {
+    let changes: Vec<String> = changes
+        .iter()
+        .map(|c| {
+            let percent = delta_percent(c.before, c.after)
+                .map(|p| format!(", {p:+.1}%"))
+                .unwrap_or_default();
+            format!(
+                "{} {:.2} -> {:.2} ms ({:+.2}{percent})",
+                c.percentile,
+                c.before,
+                c.after,
+                c.after - c.before
+            )
+        })
+        .collect();
+    eprintln!(
+        "  {icon} {} ({}, {}): {}",
+        region.code,
+        region.country,
+        region.region,
+        changes.join(", ")
+    );
+}
+
+/// Prints the comparison with the baseline to stderr, returning the comparison of every region.
+pub fn check_baseline(
+    baseline: &Baseline,
+    report: &MsReport,
+    tolerance: f64,
+) -> Vec<RegionComparison> {
+    let same_request = baseline.method.as_ref().is_none_or(|m| m == &report.method)
+        && baseline.url.as_ref().is_none_or(|u| u == &report.url);
+    if !same_request {
+        eprintln!(
+            "Warning: the baseline was measured for {} {}, not {} {}.",
+            baseline.method.as_deref().unwrap_or("?"),
+            baseline.url.as_deref().unwrap_or("?"),
+            report.method,
+            report.url
+        );
+    }
+
+    let comparisons = compare_with_baseline(&baseline.regions, &report.regions, tolerance);
+    eprintln!(
+        "📊 Compared with {}, tolerance {tolerance}%:",
+        baseline.label
+    );
+    let (mut regressed, mut improved) = (0, 0);
+    for comparison in &comparisons {
+        let region = &comparison.region;
+        match &comparison.outcome {
+            BaselineOutcome::Regressed(changes) => {
+                regressed += 1;
+                print_changes("❌", region, changes);
+            }
+            BaselineOutcome::Improved(changes) => {
+                improved += 1;
+                print_changes("🚀", region, changes);
+            }
+            BaselineOutcome::Unchanged => {}
+            BaselineOutcome::Missing => eprintln!(
+                "  ⚠️  {} ({}, {}): in the baseline but not measured in this run",
+                region.code, region.country, region.region
+            ),
+            BaselineOutcome::New => eprintln!(
+                "  ➕ {} ({}, {}): not in the baseline",
+                region.code, region.country, region.region
+            ),
+        }
+    }
+
+    if regressed > 0 {
+        eprintln!("❌ Latency regressed beyond the tolerance in {regressed} region(s).");
+    } else if improved > 0 {
+        eprintln!("✅ No latency regression, {improved} region(s) improved.");
+    } else {
+        eprintln!("✅ No latency change beyond the tolerance.");
+    }
+    comparisons
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(code: &str, amount: u64, p50: f64, p90: f64, p99: f64) -> RegionRow {
+        RegionRow {
+            code: code.to_string(),
+            country: "US".to_string(),
+            region: "Test".to_string(),
+            direction: "east".to_string(),
+            amount,
+            iterations: Some(amount),
+            assertion_failures: None,
+            first_assertion_failure: None,
+            p50,
+            p90,
+            p99,
+        }
+    }
+
+    fn outcome(before: RegionRow, after: RegionRow, tolerance: f64) -> BaselineOutcome {
+        let mut comparisons = compare_with_baseline(&[before], &[after], tolerance);
+        assert_eq!(comparisons.len(), 1);
+        comparisons.remove(0).outcome
+    }
+
+    fn percentiles(changes: &[PercentileChange]) -> Vec<&'static str> {
+        changes.iter().map(|c| c.percentile).collect()
+    }
+
+    #[test]
+    fn reports_changes_beyond_the_tolerance() {
+        let before = region("a", 100, 100.0, 200.0, 300.0);
+
+        match outcome(before.clone(), region("a", 100, 120.0, 205.0, 300.0), 10.0) {
+            BaselineOutcome::Regressed(changes) => {
+                assert_eq!(percentiles(&changes), ["p50"]);
+                assert_eq!((changes[0].before, changes[0].after), (100.0, 120.0));
+            }
+            other => panic!("expected a regression, got {other:?}"),
+        }
+        assert!(matches!(
+            outcome(before.clone(), region("a", 100, 120.0, 205.0, 300.0), 25.0),
+            BaselineOutcome::Unchanged
+        ));
+        match outcome(before, region("a", 100, 80.0, 150.0, 300.0), 10.0) {
+            BaselineOutcome::Improved(changes) => assert_eq!(percentiles(&changes), ["p50", "p90"]),
+            other => panic!("expected an improvement, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn a_regression_wins_over_an_improvement() {
+        let before = region("a", 100, 100.0, 200.0, 300.0);
+        match outcome(before, region("a", 100, 50.0, 300.0, 300.0), 10.0) {
+            BaselineOutcome::Regressed(changes) => assert_eq!(percentiles(&changes), ["p90"]),
+            other => panic!("expected a regression, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn ignores_changes_below_the_noise_floor() {
+        // +100% but only 4 ms slower.
+        let before = region("a", 100, 4.0, 8.0, 12.0);
+        assert!(matches!(
+            outcome(before.clone(), region("a", 100, 8.0, 12.0, 16.0), 10.0),
+            BaselineOutcome::Unchanged
+        ));
+        assert!(matches!(
+            outcome(before, region("a", 100, 10.0, 8.0, 12.0), 10.0),
+            BaselineOutcome::Regressed(_)
+        ));
+
+        // Anything beyond the noise floor counts when the baseline was 0 ms.
+        assert!(matches!(
+            outcome(
+                region("a", 100, 0.0, 0.0, 0.0),
+                region("a", 100, 6.0, 0.0, 0.0),
+                10.0
+            ),
+            BaselineOutcome::Regressed(_)
+        ));
+    }
+
+    #[test]
+    fn compares_p99_only_with_enough_samples() {
+        let slower_p99 = |amount| {
+            outcome(
+                region("a", amount, 100.0, 200.0, 300.0),
+                region("a", amount, 100.0, 200.0, 600.0),
+                10.0,
+            )
+        };
+        assert!(matches!(
+            slower_p99(MIN_P99_SAMPLES - 1),
+            BaselineOutcome::Unchanged
+        ));
+        match slower_p99(MIN_P99_SAMPLES) {
+            BaselineOutcome::Regressed(changes) => assert_eq!(percentiles(&changes), ["p99"]),
+            other => panic!("expected a regression, got {other:?}"),
+        }
+
+        // Both runs need enough samples.
+        assert!(matches!(
+            outcome(
+                region("a", 100, 100.0, 200.0, 300.0),
+                region("a", 10, 100.0, 200.0, 600.0),
+                10.0
+            ),
+            BaselineOutcome::Unchanged
+        ));
+    }
+
+    #[test]
+    fn reports_new_and_missing_regions() {
+        let baseline = [
+            region("a", 10, 1.0, 1.0, 1.0),
+            region("b", 10, 1.0, 1.0, 1.0),
+        ];
+        let regions = [
+            region("c", 10, 1.0, 1.0, 1.0),
+            region("a", 10, 1.0, 1.0, 1.0),
+        ];
+        let comparisons = compare_with_baseline(&baseline, &regions, 10.0);
+        let outcomes: Vec<(&str, &BaselineOutcome)> = comparisons
+            .iter()
+            .map(|c| (c.region.code.as_str(), &c.outcome))
+            .collect();
+        assert!(matches!(
+            outcomes.as_slice(),
+            [
+                ("c", BaselineOutcome::New),
+                ("a", BaselineOutcome::Unchanged),
+                ("b", BaselineOutcome::Missing)
+            ]
+        ));
+    }
+}
@@ -0,0 +1,590 @@
+use std::{
+    path::Path,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use borrow_dev::MsError;
+
+use super::{
+    OutputFormat, RegionRow, RequestArgs, RequestBody, THRESHOLD_EXIT_CODE, csv_field,
+    error_exit_code, execute_request, markdown_cell, parse_regions, prepare_all, progress_line,
+    region_to_json, summarize_error, urlencode,
+};
+
+const OPENAPI_METHODS: &[&str] = &["get", "put", "post", "delete", "options", "head", "patch"];
+
+/// Request headers that are managed by the HTTP client and must not be replayed from a HAR file.
+const HAR_SKIPPED_HEADERS: &[&str] = &["host", "content-length", "connection", "accept-encoding"];
+
+/// A single request of a batch, expanded from an OpenAPI document or a HAR file.
+#[derive(Debug, Clone)]
+pub struct BatchItem {
+    pub method: String,
+    pub url: String,
+    /// Headers in the "Key: Value" format used by `--header`.
+    pub headers: Vec<String>,
+    pub body: Option<String>,
+}
+
+pub struct BatchResult {
+    pub item: BatchItem,
+    pub outcome: Result<Vec<RegionRow>, MsError>,
+}
+
+/// The exit code of a batch: the highest error exit code when any request failed, at least
+/// `THRESHOLD_EXIT_CODE` when responses failed the assertions, 0 otherwise.
+pub fn batch_exit_code(results: &[BatchResult]) -> i32 {
+    let code = results
+        .iter()
+        .filter_map(|r| r.outcome.as_ref().err())
+        .map(error_exit_code)
+        .max()
+        .unwrap_or(0);
+    if results.iter().any(|r| r.assertion_failures() > 0) {
+        code.max(THRESHOLD_EXIT_CODE)
+    } else {
+        code
+    }
+}
+
+/// Prints the requests whose responses failed the assertions to stderr, returning how many did.
+pub fn check_batch_assertions(results: &[BatchResult]) -> usize {
+    let failed: Vec<&BatchResult> = results
+        .iter()
+        .filter(|r| r.assertion_failures() > 0)
+        .collect();
+    if failed.is_empty() {
+        return 0;
+    }
+
+    eprintln!(
+        "❌ Responses failed the assertions for {} request(s):",
+        failed.len()
+    );
+    for result in &failed {
+        let first = result
+            .outcome
+            .iter()
+            .flatten()
+            .find_map(|r| r.first_assertion_failure.as_deref())
+            .unwrap_or("-");
+        eprintln!(
+            "  {} {}: {} failed, first: {first}",
+            result.item.method,
+            result.item.url,
+            result.assertion_failures()
+        );
+    }
+    failed.len()
+}
+
+impl BatchResult {
+    /// Responses that failed the assertions, in every region.
+    fn assertion_failures(&self) -> u64 {
+        self.outcome
+            .iter()
+            .flatten()
+            .filter_map(|r| r.assertion_failures)
+            .sum()
+    }
+
+    fn worst_p99(&self) -> Option<&RegionRow> {
+        self.outcome
+            .as_ref()
+            .ok()?
+            .iter()
+            .max_by(|a, b| a.p99.total_cmp(&b.p99))
+    }
+
+    fn best_p50(&self) -> Option<&RegionRow> {
+        self.outcome
+            .as_ref()
+            .ok()?
+            .iter()
+            .min_by(|a, b| a.p50.total_cmp(&b.p50))
+    }
+}
+
+/// The expanded requests, plus warnings about the entries that had to be skipped.
+pub struct BatchExpansion {
+    pub items: Vec<BatchItem>,
+    pub warnings: Vec<String>,
+}
+
+fn read_document(path: &Path) -> Result<serde_json::Value, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read '{}': {e}", path.display()))?;
+    // JSON is valid YAML, so a single parser handles both formats.
+    serde_yaml::from_str(&content).map_err(|e| format!("failed to parse '{}': {e}", path.display()))
+}
+
+/// Follows a local `$ref` ("#/components/..."), returning the value itself otherwise.
+fn resolve_ref<'a>(
+    document: &'a serde_json::Value,
+    value: &'a serde_json::Value,
+) -> &'a serde_json::Value {
+    match value["$ref"].as_str() {
+        Some(reference) if reference.starts_with("#/") => document
+            .pointer(&reference[1..])
+            .unwrap_or(&serde_json::Value::Null),
+        _ => value,
+    }
+}
+
+fn example_to_string(example: &serde_json::Value) -> String {
+    match example {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Finds an example value for a parameter or media type object.
+fn find_example(
+    document: &serde_json::Value,
+    object: &serde_json::Value,
+) -> Option<serde_json::Value> {
+    if let Some(example) = object.get("example") {
+        return Some(example.clone());
+    }
+    if let Some(examples) = object["examples"].as_object()
+        && let Some(first) = examples.values().next()
+    {
+        return resolve_ref(document, first).get("value").cloned();
+    }
+    let schema = resolve_ref(document, &object["schema"]);
+    schema
+        .get("example")
+        .or_else(|| schema.get("default"))
+        .or_else(|| schema["enum"].get(0))
+        .cloned()
+}
+
+fn server_url(document: &serde_json::Value) -> Option<String> {
+    let server = &document["servers"][0];
+    let mut url = server["url"].as_str()?.to_string();
+    if let Some(variables) = server["variables"].as_object() {
+        for (name, variable) in variables {
+            if let Some(default) = variable["default"].as_str() {
+                url = url.replace(&format!("{{{name}}}"), default);
+            }
+        }
+    }
+    Some(url)
+}
+
+fn method_included(methods: &[String], method: &str) -> bool {
+    methods.iter().any(|m| m.eq_ignore_ascii_case(method))
+}
+
+/// Expands every operation of an OpenAPI 3 document into a request, using the examples in the
+/// document for path parameters, required query parameters and request bodies.
+pub fn expand_openapi(
+    path: &Path,
+    base_url: Option<&str>,
+    methods: &[String],
+) -> Result<BatchExpansion, String> {
+    let document = read_document(path)?;
+    let base_url = match base_url {
+        Some(url) => url.to_string(),
+        None => server_url(&document).ok_or(
+            "the OpenAPI document has no server URL, pass one with --base-url.".to_string(),
+        )?,
+    };
+    if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+        return Err(format!(
+            "the server URL '{base_url}' is not absolute, pass one with --base-url."
+        ));
+    }
+    let base_url = base_url.trim_end_matches('/');
+
+    let Some(paths) = document["paths"].as_object() else {
+        return Err("the OpenAPI document has no paths.".to_string());
+    };
+
+    let mut items = Vec::new();
+    let mut warnings = Vec::new();
+
+    for (route, path_item) in paths {
+        let path_item = resolve_ref(&document, path_item);
+        for method in OPENAPI_METHODS {
+            let Some(operation) = path_item.get(*method) else {
+                continue;
+            };
+            let method = method.to_uppercase();
+            if !method_included(methods, &method) {
+                continue;
+            }
+            let name = format!("{method} {route}");
+
+            let parameters = path_item["parameters"]
+                .as_array()
+                .into_iter()
+                .chain(operation["parameters"].as_array())
+                .flatten()
+                .map(|p| resolve_ref(&document, p));
+
+            let mut url_path = route.clone();
+            let mut query: Vec<String> = Vec::new();
+            let mut headers: Vec<String> = Vec::new();
+            let mut skipped = None;
+
+            for parameter in parameters {
+                let Some(param_name) = parameter["name"].as_str() else {
+                    continue;
+                };
+                let location = parameter["in"].as_str().unwrap_or_default();
+                let required = location == "path" || parameter["required"] == true;
+                let example = find_example(&document, parameter);
+
+                match (example, required) {
+                    (Some(example), _) => {
+                        let value = example_to_string(&example);
+                        match location {
+                            "path" => {
+                                url_path = url_path.replace(&format!("{{{param_name}}}"), &value)
+                            }
+                            "query" if required => {
+                                query.push(format!("{param_name}={}", urlencode(&value)))
+                            }
+                            "header" if required => headers.push(format!("{param_name}: {value}")),
+                            _ => {}
+                        }
+                    }
+                    (None, true) => {
+                        skipped = Some(format!(
+                            "{name}: no example for required parameter '{param_name}', skipped."
+                        ));
+                        break;
+                    }
+                    (None, false) => {}
+                }
+            }
+
+            if let Some(warning) = skipped {
+                warnings.push(warning);
+                continue;
+            }
+
+            let mut body = None;
+            let request_body = resolve_ref(&document, &operation["requestBody"]);
+            if let Some(content) = request_body["content"].as_object() {
+                let media = content
+                    .get_key_value("application/json")
+                    .or_else(|| content.iter().next());
+                let example = media.and_then(|(mime, media)| {
+                    find_example(&document, media).map(|example| (mime, example))
+                });
+                match example {
+                    Some((mime, example)) => {
+                        headers.push(format!("Content-Type: {mime}"));
+                        body = Some(example_to_string(&example));
+                    }
+                    None if request_body["required"] == true => {
+                        warnings.push(format!(
+                            "{name}: no example for the required request body, skipped."
+                        ));
+                        continue;
+                    }
+                    None => {}
+                }
+            }
+
+            if body.is_some() && method == "GET" {
+                warnings.push(format!("{name}: GET request with a body, skipped."));
+                continue;
+            }
+
+            let mut url = format!("{base_url}{url_path}");
+            if !query.is_empty() {
+                url.push('?');
+                url.push_str(&query.join("&"));
+            }
+
+            items.push(BatchItem {
+                method,
+                url,
+                headers,
+                body,
+            });
+        }
+    }
+
+    Ok(BatchExpansion { items, warnings })
+}
+
+/// Expands the entries of a HAR file into requests, skipping duplicates.
+pub fn expand_har(path: &Path, methods: &[String]) -> Result<BatchExpansion, String> {
+    let document = read_document(path)?;
+    let Some(entries) = document["log"]["entries"].as_array() else {
+        return Err("the HAR file has no entries.".to_string());
+    };
+
+    let mut items: Vec<BatchItem> = Vec::new();
+    let mut warnings = Vec::new();
+
+    for entry in entries {
+        let request = &entry["request"];
+        let (Some(method), Some(url)) = (request["method"].as_str(), request["url"].as_str())
+        else {
+            continue;
+        };
+        let method = method.to_uppercase();
+        if !method_included(methods, &method) {
+            continue;
+        }
+
+        let headers: Vec<String> = request["headers"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|h| Some((h["name"].as_str()?, h["value"].as_str()?)))
+            .filter(|(name, _)| {
+                !name.starts_with(':')
+                    && !HAR_SKIPPED_HEADERS.contains(&name.to_lowercase().as_str())
+            })
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect();
+
+        let body = request["postData"]["text"]
+            .as_str()
+            .filter(|text| !text.is_empty())
+            .map(String::from);
+        if body.is_some() && method == "GET" {
+            warnings.push(format!("{method} {url}: GET request with a body, skipped."));
+            continue;
+        }
+
+        let duplicate = items
+            .iter()
+            .any(|i| i.method == method && i.url == url && i.body == body);
+        if duplicate {
+            continue;
+        }
+
+        items.push(BatchItem {
+            method,
+            url: url.to_string(),
+            headers,
+            body,
+        });
+    }
+
+    Ok(BatchExpansion { items, warnings })
+}
+
+/// Measures every item, at most `concurrency` at the same time, keeping the input order.
+pub fn run_batch(
+    items: Vec<BatchItem>,
+    args: &RequestArgs,
+    concurrency: usize,
+) -> Vec<BatchResult> {
+    let requests = prepare_all(
+        items
+            .iter()
+            .map(|item| {
+                let mut item_args = args.clone();
+                item_args.method = item.method.clone();
+                // Headers given with --header are applied last, so they override the expanded ones.
+                item_args.header.splice(0..0, item.headers.clone());
+                item_args.body = item.body.clone().map(RequestBody::inline);
+                (item.url.as_str(), item_args)
+            })
+            .collect(),
+    );
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<BatchResult>>> = Mutex::new(items.iter().map(|_| None).collect());
+    let total = items.len();
+
+    std::thread::scope(|s| {
+        for _ in 0..concurrency.clamp(1, total.max(1)) {
+            s.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let (Some(item), Some((item_args, request))) =
+                        (items.get(index), requests.get(index))
+                    else {
+                        break;
+                    };
+
+                    progress_line(&format!(
+                        "[{}/{}] {} {}",
+                        index + 1,
+                        total,
+                        item.method,
+                        item.url
+                    ));
+
+                    let outcome = execute_request(item_args, request)
+                        .map(|response| parse_regions(&response));
+
+                    results.lock().unwrap()[index] = Some(BatchResult {
+                        item: item.clone(),
+                        outcome,
+                    });
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Prints one row per request, slowest worst-region p99 first and failed requests last.
+pub fn print_batch_report(results: &mut [BatchResult], format: OutputFormat) {
+    results.sort_by(|a, b| {
+        let p99_a = a.worst_p99().map_or(f64::MIN, |r| r.p99);
+        let p99_b = b.worst_p99().map_or(f64::MIN, |r| r.p99);
+        p99_b.total_cmp(&p99_a)
+    });
+
+    match format {
+        OutputFormat::Table => print_table(results),
+        OutputFormat::Json => print_json(results),
+        OutputFormat::Csv => print_csv(results),
+        OutputFormat::Markdown => print_markdown(results),
+    }
+}
+
+fn print_table(results: &[BatchResult]) {
+    println!("=================================================================================");
+    println!("🏁 API latency stats for {} request(s)", results.len());
+    println!();
+    println!(
+        "{:<4} {:<7} {:>7} {:>10} {:>10}  {:<26} URL",
+        "#", "METHOD", "REGIONS", "BEST p50", "WORST p99", "WORST REGION"
+    );
+    println!("{}", "-".repeat(104));
+
+    for (i, result) in results.iter().enumerate() {
+        match &result.outcome {
+            Ok(regions) => {
+                let best = result.best_p50();
+                let worst = result.worst_p99();
+                println!(
+                    "{:<4} {:<7} {:>7} {:>10} {:>10}  {:<26} {}",
+                    i + 1,
+                    result.item.method,
+                    regions.len(),
+                    best.map_or("-".to_string(), |r| format!("{:.2}", r.p50)),
+                    worst.map_or("-".to_string(), |r| format!("{:.2}", r.p99)),
+                    worst.map_or("-", |r| r.code.as_str()),
+                    result.item.url
+                );
+            }
+            Err(e) => {
+                println!(
+                    "{:<4} {:<7} {:>7} {:>10} {:>10}  {:<26} {}",
+                    i + 1,
+                    result.item.method,
+                    "-",
+                    "-",
+                    "-",
+                    "❌ failed",
+                    result.item.url
+                );
+                println!("     Error: {e}");
+            }
+        }
+    }
+
+    println!();
+    println!("=================================================================================");
+    println!("❤️  By Borrow.dev \u{21C0} Open-Source Tools for Web Developers");
+    println!();
+}
+
+fn print_json(results: &[BatchResult]) {
+    let output: Vec<_> = results
+        .iter()
+        .map(|result| {
+            let (regions, error) = match &result.outcome {
+                Ok(regions) => (
+                    Some(regions.iter().map(region_to_json).collect::<Vec<_>>()),
+                    None,
+                ),
+                Err(e) => (None, Some(format!("Error: {e}"))),
+            };
+            serde_json::json!({
+                "method": result.item.method,
+                "url": result.item.url,
+                "worstP99": result.worst_p99().map(|r| r.p99),
+                "worstRegion": result.worst_p99().map(|r| r.code.clone()),
+                "regions": regions,
+                "error": error,
+            })
+        })
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&output).unwrap());
+}
+
+fn print_csv(results: &[BatchResult]) {
+    println!(
+        "method,url,regions,amount,iterations,assertionFailures,best_p50,worst_p99,worst_region,error"
+    );
+    for result in results {
+        let best = result.best_p50();
+        let worst = result.worst_p99();
+        let regions = result.outcome.as_deref().unwrap_or_default();
+        // Totals are left empty when no region reported them.
+        let total = |value: fn(&RegionRow) -> Option<u64>| {
+            regions
+                .iter()
+                .filter_map(value)
+                .reduce(|a, b| a + b)
+                .map_or(String::new(), |total| total.to_string())
+        };
+        println!(
+            "{},{},{},{},{},{},{},{},{},{}",
+            csv_field(&result.item.method),
+            csv_field(&result.item.url),
+            regions.len(),
+            total(|r| Some(r.amount)),
+            total(|r| r.iterations),
+            total(|r| r.assertion_failures),
+            best.map_or(String::new(), |r| format!("{:.2}", r.p50)),
+            worst.map_or(String::new(), |r| format!("{:.2}", r.p99)),
+            worst.map_or(String::new(), |r| csv_field(&r.code)),
+            result
+                .outcome
+                .as_ref()
+                .err()
+                .map_or(String::new(), |e| csv_field(&summarize_error(&format!(
+                    "Error: {e}"
+                ))))
+        );
+    }
+}
+
+fn print_markdown(results: &[BatchResult]) {
+    println!("| # | Method | URL | Regions | Best p50 (ms) | Worst p99 (ms) | Worst region |");
+    println!("|--:|--------|-----|--------:|--------------:|---------------:|--------------|");
+    for (i, result) in results.iter().enumerate() {
+        let best = result.best_p50();
+        let worst = result.worst_p99();
+        println!(
+            "| {} | {} | {} | {} | {} | {} | {} |",
+            i + 1,
+            result.item.method,
+            markdown_cell(&result.item.url),
+            result
+                .outcome
+                .as_ref()
+                .map_or("-".to_string(), |r| r.len().to_string()),
+            best.map_or("-".to_string(), |r| format!("{:.2}", r.p50)),
+            worst.map_or("-".to_string(), |r| format!("{:.2}", r.p99)),
+            match &result.outcome {
+                Ok(_) => worst.map_or("-".to_string(), |r| format!("`{}`", r.code)),
+                Err(e) => format!("❌ {}", markdown_cell(&summarize_error(&e.to_string()))),
+            }
+        );
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    fmt,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use super::urlencode;
+
+/// Where the body of a measured request comes from.
+#[derive(Debug, Clone)]
+pub enum BodySource {
+    Inline,
+    File(PathBuf),
+    Stdin,
+    Form,
+    Multipart,
+}
+
+impl fmt::Display for BodySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inline => write!(f, "inline"),
+            Self::File(path) => write!(f, "file {}", path.display()),
+            Self::Stdin => write!(f, "stdin"),
+            Self::Form => write!(f, "form"),
+            Self::Multipart => write!(f, "multipart form"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestBody {
+    pub content: String,
+    /// Content-Type sent when none is given with `--header`.
+    pub content_type: Option<String>,
+    pub source: BodySource,
+}
+
+impl RequestBody {
+    /// An inline body, sent without a Content-Type unless one is given with `--header`.
+    pub fn inline(content: String) -> Self {
+        Self {
+            content,
+            content_type: None,
+            source: BodySource::Inline,
+        }
+    }
+}
+
+/// A file given with `--form-file field=@path`.
+#[derive(Debug, Clone)]
+pub struct FormFile {
+    pub field: String,
+    pub path: PathBuf,
+}
+
+fn read_text_file(path: &Path) -> Result<String, String> {
+    let bytes =
+        std::fs::read(path).map_err(|e| format!("failed to read '{}': {e}", path.display()))?;
+    // The ms API takes the body as a JSON string, so it has to be valid UTF-8.
+    String::from_utf8(bytes).map_err(|_| {
+        format!(
+            "'{}' is not a text file, binary bodies are not supported.",
+            path.display()
+        )
+    })
+}
+
+fn mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "txt" => "text/plain",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "yaml" | "yml" => "application/yaml",
+        _ => return None,
+    })
+}
+
+fn detect_content_type(content: &str, path: Option<&Path>) -> Option<String> {
+    if let Some(mime) = path.and_then(mime_type) {
+        return Some(mime.to_string());
+    }
+    match serde_json::from_str::<serde_json::Value>(content) {
+        Ok(serde_json::Value::Object(_) | serde_json::Value::Array(_)) => {
+            Some("application/json".to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Parses `--body`: inline content, "@path" to read a file, or "@-" to read stdin.
+pub fn parse_body(value: &str) -> Result<RequestBody, String> {
+    match value.strip_prefix('@') {
+        Some("-") => {
+            let mut content = String::new();
+            std::io::stdin()
+                .read_to_string(&mut content)
+                .map_err(|e| format!("failed to read the body from stdin: {e}"))?;
+            Ok(RequestBody {
+                content_type: detect_content_type(&content, None),
+                content,
+                source: BodySource::Stdin,
+            })
+        }
+        Some(path) => {
+            let path = PathBuf::from(path);
+            let content = read_text_file(&path)?;
+            Ok(RequestBody {
+                content_type: detect_content_type(&content, Some(&path)),
+                content,
+                source: BodySource::File(path),
+            })
+        }
+        None => Ok(RequestBody::inline(value.to_string())),
+    }
+}
+
+/// Parses `--form key=value`.
+pub fn parse_form_field(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!(
+            "invalid form field '{value}', expected 'key=value'"
+        )),
+    }
+}
+
+/// Parses `--form-file field=@path`.
+pub fn parse_form_file(value: &str) -> Result<FormFile, String> {
+    match value.split_once("=@") {
+        Some((field, path)) if !field.is_empty() && !path.is_empty() => Ok(FormFile {
+            field: field.to_string(),
+            path: PathBuf::from(path),
+        }),
+        _ => Err(format!(
+            "invalid form file '{value}', expected 'field=@path'"
+        )),
+    }
+}
+
+/// Builds an `application/x-www-form-urlencoded` body.
+pub fn form_body(fields: &[(String, String)]) -> RequestBody {
+    let content = fields
+        .iter()
+        .map(|(key, value)| format!("{}={}", urlencode(key), urlencode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+    RequestBody {
+        content,
+        content_type: Some("application/x-www-form-urlencoded".to_string()),
+        source: BodySource::Form,
+    }
+}
+
+/// Builds a `multipart/form-data` body from text fields and files.
+pub fn multipart_body(
+    fields: &[(String, String)],
+    files: &[FormFile],
+) -> Result<RequestBody, String> {
+    let mut parts: Vec<(String, String)> = fields
+        .iter()
+        .map(|(name, value)| {
+            (
+                format!("Content-Disposition: form-data; name=\"{name}\""),
+                value.clone(),
+            )
+        })
+        .collect();
+
+    for file in files {
+        let content = read_text_file(&file.path)?;
+        let filename = file
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mime = mime_type(&file.path).unwrap_or("application/octet-stream");
+        parts.push((
+            format!(
+                "Content-Disposition: form-data; name=\"{}\"; filename=\"{filename}\"\r\nContent-Type: {mime}",
+                file.field
+            ),
+            content,
+        ));
+    }
+
+    // Derived from the content rather than random, so that the request hash stays stable.
+    let digest = md5::compute(
+        parts
+            .iter()
+            .flat_map(|(headers, content)| [headers.as_str(), content.as_str()])
+            .collect::<String>(),
+    );
+    let boundary = format!("borrow-ms-{digest:x}");
+
+    let mut content = String::new();
+    for (headers, value) in &parts {
+        content.push_str(&format!("--{boundary}\r\n{headers}\r\n\r\n{value}\r\n"));
+    }
+    content.push_str(&format!("--{boundary}--\r\n"));
+
+    Ok(RequestBody {
+        content,
+        content_type: Some(format!("multipart/form-data; boundary={boundary}")),
+        source: BodySource::Multipart,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_bodies_have_no_content_type() {
+        assert_eq!(parse_body(r#"{"a": 1}"#).unwrap().content_type, None);
+        assert_eq!(RequestBody::inline("[1, 2]".to_string()).content_type, None);
+    }
+
+    #[test]
+    fn file_bodies_are_typed_by_extension_or_content() {
+        let dir = std::env::temp_dir().join(format!("borrow-body-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let content_type = |name: &str, content: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            parse_body(&format!("@{}", path.display()))
+                .unwrap()
+                .content_type
+        };
+        assert_eq!(
+            content_type("body.xml", "<a/>").as_deref(),
+            Some("application/xml")
+        );
+        assert_eq!(
+            content_type("body", r#"{"a": 1}"#).as_deref(),
+            Some("application/json")
+        );
+        assert_eq!(content_type("body.bin", "plain"), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn form_bodies_are_url_encoded() {
+        let body = form_body(&[("a b".to_string(), "c&d".to_string())]);
+        assert_eq!(body.content, "a%20b=c%26d");
+        assert_eq!(
+            body.content_type.as_deref(),
+            Some("application/x-www-form-urlencoded")
+        );
+    }
+}
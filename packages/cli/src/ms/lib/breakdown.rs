@@ -0,0 +1,527 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    time::{Duration, Instant},
+};
+
+use borrow_dev::MsError;
+
+use super::{PreparedRequest, percentile};
+
+/// Percentiles of a phase of the request, in milliseconds.
+#[derive(Debug, Clone)]
+pub struct PhaseRow {
+    pub phase: &'static str,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+/// Where the time of a request goes, measured from this machine.
+#[derive(Debug, Clone)]
+pub struct Breakdown {
+    pub iterations: u32,
+    /// Requests that got a successful (2xx) response.
+    pub amount: u32,
+    pub phases: Vec<PhaseRow>,
+}
+
+/// Timings of a single request, in milliseconds.
+struct PhaseTimings {
+    dns: f64,
+    connect: f64,
+    /// `None` for plain HTTP.
+    tls: Option<f64>,
+    /// From sending the request to the first byte of the response.
+    ttfb: f64,
+    /// From the first to the last byte of the response.
+    download: f64,
+    status: u16,
+}
+
+fn millis(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+fn remaining(deadline: Instant) -> std::io::Result<Duration> {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        return Err(std::io::ErrorKind::TimedOut.into());
+    }
+    Ok(remaining)
+}
+
+/// How the end of a response body is found.
+enum Framing {
+    Empty,
+    Length(u64),
+    Chunked,
+    UntilClose,
+}
+
+/// Reads the status line and headers of a response, up to and including the empty line.
+fn read_head(reader: &mut impl BufRead) -> std::io::Result<String> {
+    let mut head = String::new();
+    loop {
+        let mut line = Vec::new();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "connection closed before the end of the response headers",
+            ));
+        }
+        let line = String::from_utf8_lossy(&line);
+        if line.trim_end().is_empty() {
+            return Ok(head);
+        }
+        head.push_str(&line);
+    }
+}
+
+fn response_status(head: &str) -> std::io::Result<u16> {
+    head.split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid HTTP response")
+        })
+}
+
+/// Finds how the body of a response ends, following RFC 9112 section 6.3.
+fn body_framing(head: &str, status: u16, head_request: bool) -> std::io::Result<Framing> {
+    if head_request || (100..200).contains(&status) || status == 204 || status == 304 {
+        return Ok(Framing::Empty);
+    }
+    let header = |name: &str| {
+        head.lines().skip(1).find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case(name)
+                .then(|| value.trim().to_string())
+        })
+    };
+    if let Some(encoding) = header("Transfer-Encoding") {
+        if encoding
+            .rsplit(',')
+            .next()
+            .is_some_and(|last| last.trim().eq_ignore_ascii_case("chunked"))
+        {
+            return Ok(Framing::Chunked);
+        }
+        return Ok(Framing::UntilClose);
+    }
+    match header("Content-Length") {
+        Some(length) => length.parse().map(Framing::Length).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid Content-Length '{length}'"),
+            )
+        }),
+        None => Ok(Framing::UntilClose),
+    }
+}
+
+/// Reads and drops exactly `length` bytes.
+fn skip_bytes(reader: &mut impl BufRead, length: u64) -> std::io::Result<()> {
+    let read = std::io::copy(&mut reader.take(length), &mut std::io::sink())?;
+    if read < length {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "connection closed before the end of the response body",
+        ));
+    }
+    Ok(())
+}
+
+fn skip_chunked_body(reader: &mut impl BufRead) -> std::io::Result<()> {
+    let invalid_chunk = || {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "invalid chunked response body",
+        )
+    };
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_chunk());
+        }
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = u64::from_str_radix(size, 16).map_err(|_| invalid_chunk())?;
+        if size == 0 {
+            // The last chunk is followed by optional trailers and an empty line.
+            read_head(reader)?;
+            return Ok(());
+        }
+        skip_bytes(reader, size)?;
+        line.clear();
+        reader.read_line(&mut line)?;
+        if !line.trim_end().is_empty() {
+            return Err(invalid_chunk());
+        }
+    }
+}
+
+/// Sends the request and reads the whole response, returning the status, TTFB and download time.
+fn exchange(
+    mut stream: impl Read + Write,
+    request: &[u8],
+    head_request: bool,
+) -> std::io::Result<(u16, f64, f64)> {
+    // Started before sending, since the response may already be there when the write returns.
+    let start = Instant::now();
+    stream.write_all(request)?;
+    stream.flush()?;
+
+    let mut reader = BufReader::with_capacity(16 * 1024, stream);
+    if reader.fill_buf()?.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "connection closed without a response",
+        ));
+    }
+    let ttfb = millis(start);
+
+    let start = Instant::now();
+    let (status, framing) = loop {
+        let head = read_head(&mut reader)?;
+        let status = response_status(&head)?;
+        // Interim responses such as `100 Continue` come before the actual one.
+        if (100..200).contains(&status) && status != 101 {
+            continue;
+        }
+        break (status, body_framing(&head, status, head_request)?);
+    };
+    match framing {
+        Framing::Empty => {}
+        Framing::Length(length) => skip_bytes(&mut reader, length)?,
+        Framing::Chunked => skip_chunked_body(&mut reader)?,
+        Framing::UntilClose => {
+            std::io::copy(&mut reader, &mut std::io::sink())?;
+        }
+    }
+    let download = millis(start);
+
+    Ok((status, ttfb, download))
+}
+
+/// Headers that describe the connection or the framing of the body, which are set here instead.
+const MANAGED_HEADERS: [&str; 4] = ["Host", "Connection", "Content-Length", "Transfer-Encoding"];
+
+/// Builds the HTTP/1.1 request, with the body sent as-is after a `Content-Length`.
+fn request_payload(
+    method: &str,
+    url: &reqwest::Url,
+    headers: &BTreeMap<String, String>,
+    body: &str,
+) -> Vec<u8> {
+    let host = url.host_str().unwrap_or_default();
+    let host_header = match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_string(),
+    };
+    let mut head = format!(
+        "{method} {}{} HTTP/1.1\r\nHost: {host_header}\r\n",
+        url.path(),
+        url.query().map(|q| format!("?{q}")).unwrap_or_default()
+    );
+    for (name, value) in headers {
+        if MANAGED_HEADERS
+            .iter()
+            .any(|managed| name.eq_ignore_ascii_case(managed))
+        {
+            continue;
+        }
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    if !body.is_empty() {
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    // A new connection for every iteration, so that each one includes DNS, connect and TLS.
+    head.push_str("Connection: close\r\n\r\n");
+    let mut payload = head.into_bytes();
+    payload.extend_from_slice(body.as_bytes());
+    payload
+}
+
+/// The name the TLS certificate is checked against, IPv6 addresses without their brackets.
+fn tls_domain(url: &reqwest::Url) -> &str {
+    let host = url.host_str().unwrap_or_default();
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+fn measure_phases(
+    request: &PreparedRequest,
+    url: &reqwest::Url,
+    tls_connector: Option<&native_tls::TlsConnector>,
+    deadline: Instant,
+) -> std::io::Result<PhaseTimings> {
+    let start = Instant::now();
+    let addresses = url.socket_addrs(|| None)?;
+    let dns = millis(start);
+
+    let start = Instant::now();
+    let mut last_error = None;
+    let mut stream = None;
+    for address in &addresses {
+        match TcpStream::connect_timeout(address, remaining(deadline)?) {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    let stream = match (stream, last_error) {
+        (Some(stream), _) => stream,
+        (None, Some(e)) => return Err(e),
+        (None, None) => return Err(std::io::ErrorKind::NotFound.into()),
+    };
+    let connect = millis(start);
+    stream.set_read_timeout(Some(remaining(deadline)?))?;
+    stream.set_write_timeout(Some(remaining(deadline)?))?;
+    stream.set_nodelay(true)?;
+
+    let measure_request = &request.params.measure_request;
+    let payload = request_payload(
+        &request.method,
+        url,
+        &measure_request.headers,
+        measure_request.body.as_deref().unwrap_or_default(),
+    );
+    let head_request = request.method.eq_ignore_ascii_case("HEAD");
+
+    let (tls, (status, ttfb, download)) = match tls_connector {
+        Some(connector) => {
+            let start = Instant::now();
+            let stream = connector
+                .connect(tls_domain(url), stream)
+                .map_err(std::io::Error::other)?;
+            let tls = millis(start);
+            (Some(tls), exchange(stream, &payload, head_request)?)
+        }
+        None => (None, exchange(stream, &payload, head_request)?),
+    };
+
+    Ok(PhaseTimings {
+        dns,
+        connect,
+        tls,
+        ttfb,
+        download,
+        status,
+    })
+}
+
+/// Runs the request from this machine, timing DNS, TCP connect, TLS, TTFB and download separately.
+pub fn measure_breakdown(
+    request: &PreparedRequest,
+    iterations: u32,
+    timeout: Duration,
+) -> Result<Breakdown, MsError> {
+    let url = reqwest::Url::parse(&request.url)
+        .map_err(|e| MsError::InvalidParams(format!("invalid URL: {e}")))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(MsError::InvalidParams(format!(
+            "cannot break down '{}', only http and https URLs are supported",
+            request.url
+        )));
+    }
+
+    // Built once up front, so that loading the root certificates isn't timed as part of TLS.
+    let tls_connector = if url.scheme() == "https" {
+        let connector = native_tls::TlsConnector::new()
+            .map_err(|e| MsError::Upstream(format!("failed to set up TLS: {e}")))?;
+        Some(connector)
+    } else {
+        None
+    };
+
+    // The timeout applies to all the iterations together, like for `--local`.
+    let deadline = Instant::now() + timeout;
+    let mut timings = Vec::with_capacity(iterations as usize);
+    let mut errors = Vec::new();
+    let mut amount = 0;
+    for _ in 0..iterations {
+        match measure_phases(request, &url, tls_connector.as_ref(), deadline) {
+            Ok(timing) => {
+                if (200..300).contains(&timing.status) {
+                    amount += 1;
+                }
+                timings.push(timing);
+            }
+            Err(_) if Instant::now() >= deadline => return Err(MsError::Timeout(timeout)),
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+
+    if timings.is_empty() {
+        return Err(MsError::Upstream(format!(
+            "Upstream error: {}",
+            errors.join(", ")
+        )));
+    }
+
+    let phase = |name: &'static str, value: fn(&PhaseTimings) -> Option<f64>| {
+        let mut values: Vec<f64> = timings.iter().filter_map(value).collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+        Some(PhaseRow {
+            phase: name,
+            p50: percentile(&values, 50.0),
+            p90: percentile(&values, 90.0),
+            p99: percentile(&values, 99.0),
+        })
+    };
+    let phases = [
+        phase("DNS", |t| Some(t.dns)),
+        phase("Connect", |t| Some(t.connect)),
+        phase("TLS", |t| t.tls),
+        phase("TTFB", |t| Some(t.ttfb)),
+        phase("Download", |t| Some(t.download)),
+        phase("Total", |t| {
+            Some(t.dns + t.connect + t.tls.unwrap_or_default() + t.ttfb + t.download)
+        }),
+    ];
+
+    Ok(Breakdown {
+        iterations,
+        amount,
+        phases: phases.into_iter().flatten().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// A connection that answers with `response`, then stays open unless `closes` is set, failing
+    /// reads like a read timeout would.
+    struct Connection {
+        response: Cursor<Vec<u8>>,
+        closes: bool,
+        sent: Vec<u8>,
+    }
+
+    impl Connection {
+        fn new(response: &str) -> Self {
+            Self {
+                response: Cursor::new(response.as_bytes().to_vec()),
+                closes: false,
+                sent: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Connection {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.response.read(buf)? {
+                0 if !self.closes => Err(std::io::ErrorKind::WouldBlock.into()),
+                read => Ok(read),
+            }
+        }
+    }
+
+    impl Write for Connection {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.sent.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn status_of(response: &str, head_request: bool) -> std::io::Result<u16> {
+        exchange(
+            Connection::new(response),
+            b"GET / HTTP/1.1\r\n\r\n",
+            head_request,
+        )
+        .map(|(status, _, _)| status)
+    }
+
+    #[test]
+    fn reads_bodies_by_content_length() {
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+        assert_eq!(status_of(response, false).unwrap(), 200);
+
+        let truncated = "HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello";
+        assert!(status_of(truncated, false).is_err());
+    }
+
+    #[test]
+    fn reads_chunked_bodies() {
+        let response = "HTTP/1.1 201 Created\r\ntransfer-encoding: chunked\r\n\r\n\
+            5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\nX-Trailer: 1\r\n\r\n";
+        assert_eq!(status_of(response, false).unwrap(), 201);
+
+        let invalid = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n";
+        assert!(status_of(invalid, false).is_err());
+    }
+
+    #[test]
+    fn skips_interim_responses_and_bodyless_statuses() {
+        let response = "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 204 No Content\r\n\r\n";
+        assert_eq!(status_of(response, false).unwrap(), 204);
+
+        let head = "HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n";
+        assert_eq!(status_of(head, true).unwrap(), 200);
+    }
+
+    #[test]
+    fn reads_until_close_without_framing() {
+        let mut connection = Connection::new("HTTP/1.1 200 OK\r\n\r\nhello");
+        connection.closes = true;
+        let (status, _, _) = exchange(connection, b"GET / HTTP/1.1\r\n\r\n", false).unwrap();
+        assert_eq!(status, 200);
+
+        // Without framing, a connection that stays open never ends the body.
+        assert!(status_of("HTTP/1.1 200 OK\r\n\r\nhello", false).is_err());
+    }
+
+    #[test]
+    fn handles_ipv6_hosts() {
+        let url = reqwest::Url::parse("https://[::1]:8443/health").unwrap();
+        assert_eq!(tls_domain(&url), "::1");
+        assert_eq!(
+            url.socket_addrs(|| None).unwrap(),
+            ["[::1]:8443".parse().unwrap()]
+        );
+        assert_eq!(
+            tls_domain(&reqwest::Url::parse("https://example.com").unwrap()),
+            "example.com"
+        );
+
+        let payload = request_payload("GET", &url, &BTreeMap::new(), "");
+        assert!(
+            String::from_utf8(payload)
+                .unwrap()
+                .contains("\r\nHost: [::1]:8443\r\n")
+        );
+    }
+
+    #[test]
+    fn request_payload_sets_the_connection_headers() {
+        let url = reqwest::Url::parse("http://example.com:8080/items?page=2").unwrap();
+        let headers = BTreeMap::from([
+            ("host".to_string(), "other.example.com".to_string()),
+            ("Connection".to_string(), "keep-alive".to_string()),
+            ("Content-Length".to_string(), "1".to_string()),
+            ("X-Token".to_string(), "secret".to_string()),
+        ]);
+        let payload = request_payload("POST", &url, &headers, "{}");
+        assert_eq!(
+            String::from_utf8(payload).unwrap(),
+            "POST /items?page=2 HTTP/1.1\r\nHost: example.com:8080\r\nX-Token: secret\r\n\
+             Content-Length: 2\r\nConnection: close\r\n\r\n{}"
+        );
+    }
+}
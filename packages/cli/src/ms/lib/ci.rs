@@ -0,0 +1,246 @@
+use std::{fmt::Write, path::PathBuf};
+
+use borrow_dev::MsError;
+use chrono::Utc;
+use clap::Args;
+
+use super::{BaselineOutcome, RegionComparison, RegionRow, Violation, delta_percent};
+
+#[derive(Args, Debug, Default, Clone)]
+pub struct CiArgs {
+    /// Write a JUnit XML report with a test case per region, for CI test report views
+    #[arg(long = "junit")]
+    pub junit: Option<PathBuf>,
+
+    /// Print GitHub Actions workflow commands, shown as annotations on the run
+    #[arg(long = "github-annotations", default_value_t = false)]
+    pub github_annotations: bool,
+}
+
+/// A region of a run and why it failed, if it did.
+pub struct CiRegion<'a> {
+    pub region: &'a RegionRow,
+    /// Failed assertions, exceeded thresholds and regressions from the baseline.
+    pub failures: Vec<String>,
+}
+
+/// A measured request, as reported to CI.
+pub struct CiRun<'a> {
+    /// The scenario name for `borrow ms run`, the method and URL otherwise.
+    pub name: String,
+    pub outcome: Result<Vec<CiRegion<'a>>, &'a MsError>,
+}
+
+/// Collects the failures of every region from its assertions, the threshold violations and
+/// baseline comparisons.
+pub fn ci_regions<'a>(
+    regions: &'a [RegionRow],
+    violations: &[Violation],
+    comparisons: &[RegionComparison],
+) -> Vec<CiRegion<'a>> {
+    regions
+        .iter()
+        .map(|region| {
+            let mut failures: Vec<String> = violations
+                .iter()
+                .filter(|v| v.region.code == region.code)
+                .map(|v| {
+                    format!(
+                        "{} {:.2} ms exceeds the {:.2} ms threshold",
+                        v.percentile, v.value, v.max
+                    )
+                })
+                .collect();
+            if let Some(failed) = region.assertion_failures.filter(|&f| f > 0) {
+                failures.push(format!(
+                    "{failed} response(s) failed the assertions, first: {}",
+                    region.first_assertion_failure.as_deref().unwrap_or("-")
+                ));
+            }
+            for comparison in comparisons.iter().filter(|c| c.region.code == region.code) {
+                if let BaselineOutcome::Regressed(changes) = &comparison.outcome {
+                    failures.extend(changes.iter().map(|c| {
+                        let percent = delta_percent(c.before, c.after)
+                            .map(|p| format!(", {p:+.1}%"))
+                            .unwrap_or_default();
+                        format!(
+                            "{} regressed from {:.2} ms to {:.2} ms ({:+.2}{percent}) since the baseline",
+                            c.percentile,
+                            c.before,
+                            c.after,
+                            c.after - c.before
+                        )
+                    }));
+                }
+            }
+            CiRegion { region, failures }
+        })
+        .collect()
+}
+
+fn region_label(region: &RegionRow) -> String {
+    format!("{} ({}, {})", region.code, region.country, region.region)
+}
+
+fn region_stats(region: &RegionRow) -> String {
+    format!(
+        "p50 {:.2} ms, p90 {:.2} ms, p99 {:.2} ms, {} successful",
+        region.p50,
+        region.p90,
+        region.p99,
+        region.iterations_label()
+    )
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Renders the runs as a JUnit XML report, with a test suite per run and a test case per region.
+pub fn render_junit(runs: &[CiRun]) -> String {
+    let mut suites = String::new();
+    let (mut total_tests, mut total_failures, mut total_errors) = (0, 0, 0);
+    let timestamp = Utc::now().format("%Y-%m-%dT%H:%M:%S");
+
+    for run in runs {
+        let name = xml_escape(&run.name);
+        let mut cases = String::new();
+        let (mut tests, mut failures, mut errors) = (0, 0, 0);
+        match &run.outcome {
+            Ok(regions) => {
+                for CiRegion {
+                    region,
+                    failures: messages,
+                } in regions
+                {
+                    tests += 1;
+                    let _ = writeln!(
+                        cases,
+                        "    <testcase classname=\"{name}\" name=\"{}\">",
+                        xml_escape(&region_label(region))
+                    );
+                    if region.amount == 0 {
+                        errors += 1;
+                        let _ = writeln!(
+                            cases,
+                            "      <error type=\"NoSuccessfulRequest\" message=\"No request succeeded from this region\"/>"
+                        );
+                    } else if !messages.is_empty() {
+                        failures += 1;
+                        let _ = writeln!(
+                            cases,
+                            "      <failure type=\"LatencyCheck\" message=\"{}\">{}</failure>",
+                            xml_escape(&messages.join("; ")),
+                            xml_escape(&messages.join("\n"))
+                        );
+                    }
+                    let _ = writeln!(
+                        cases,
+                        "      <system-out>{}</system-out>\n    </testcase>",
+                        xml_escape(&region_stats(region))
+                    );
+                }
+            }
+            Err(e) => {
+                tests += 1;
+                errors += 1;
+                let _ = writeln!(
+                    cases,
+                    "    <testcase classname=\"{name}\" name=\"measurement\">\n      <error type=\"{}\" message=\"{}\"/>\n    </testcase>",
+                    xml_escape(e.code().unwrap_or("ERROR")),
+                    xml_escape(&e.to_string())
+                );
+            }
+        }
+
+        let _ = write!(
+            suites,
+            "  <testsuite name=\"{name}\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\" timestamp=\"{timestamp}\">\n{cases}  </testsuite>\n"
+        );
+        total_tests += tests;
+        total_failures += failures;
+        total_errors += errors;
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"borrow ms\" tests=\"{total_tests}\" failures=\"{total_failures}\" errors=\"{total_errors}\">\n{suites}</testsuites>\n"
+    )
+}
+
+/// Escapes the message of a workflow command.
+fn command_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property of a workflow command, e.g. its title.
+fn command_property(value: &str) -> String {
+    command_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Prints GitHub Actions `::error` and `::warning` workflow commands for the failing runs and regions.
+pub fn print_github_annotations(runs: &[CiRun]) {
+    let annotate = |level: &str, title: &str, message: &str| {
+        eprintln!(
+            "::{level} title={}::{}",
+            command_property(title),
+            command_data(message)
+        );
+    };
+
+    for run in runs {
+        let regions = match &run.outcome {
+            Ok(regions) => regions,
+            Err(e) => {
+                annotate("error", &format!("{} failed", run.name), &e.to_string());
+                continue;
+            }
+        };
+        for CiRegion { region, failures } in regions {
+            let label = region_label(region);
+            if region.amount == 0 {
+                annotate(
+                    "error",
+                    &format!("{}: no successful request", run.name),
+                    &format!("{label}: no request succeeded from this region"),
+                );
+            } else if !failures.is_empty() {
+                annotate(
+                    "error",
+                    &format!("{}: latency check failed", run.name),
+                    &format!(
+                        "{label}: {} ({})",
+                        failures.join("; "),
+                        region_stats(region)
+                    ),
+                );
+            } else if region.iterations.is_some_and(|i| region.amount < i) {
+                annotate(
+                    "warning",
+                    &format!("{}: failed requests", run.name),
+                    &format!("{label}: some requests failed ({})", region_stats(region)),
+                );
+            }
+        }
+    }
+}
+
+/// Writes the JUnit report and prints the annotations requested on the command line.
+pub fn report_to_ci(args: &CiArgs, runs: &[CiRun]) -> Result<(), String> {
+    if args.github_annotations {
+        print_github_annotations(runs);
+    }
+    if let Some(path) = &args.junit {
+        std::fs::write(path, render_junit(runs))
+            .map_err(|e| format!("failed to write '{}': {e}", path.display()))?;
+        eprintln!("JUnit report saved to {}", path.display());
+    }
+    Ok(())
+}
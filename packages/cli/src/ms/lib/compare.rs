@@ -0,0 +1,234 @@
+use super::{
+    OutputFormat, Percentile, RegionRow, csv_field, format_delta, markdown_cell, region_flag,
+};
+
+/// The measurement of a single URL taking part in a comparison.
+pub struct ComparedUrl {
+    pub url: String,
+    pub regions: Vec<RegionRow>,
+}
+
+/// Column labels for the compared URLs: A, B, C...
+fn url_label(index: usize) -> String {
+    char::from(b'A' + (index % 26) as u8).to_string()
+}
+
+/// Regions measured for at least one URL, starting with the reference (first) URL's regions.
+fn merged_regions(urls: &[ComparedUrl]) -> Vec<&RegionRow> {
+    let mut merged: Vec<&RegionRow> = Vec::new();
+    for compared in urls {
+        for region in &compared.regions {
+            if !merged.iter().any(|r| r.code == region.code) {
+                merged.push(region);
+            }
+        }
+    }
+    merged
+}
+
+fn find_region<'a>(compared: &'a ComparedUrl, code: &str) -> Option<&'a RegionRow> {
+    compared.regions.iter().find(|r| r.code == code)
+}
+
+pub fn delta_percent(before: f64, after: f64) -> Option<f64> {
+    (before != 0.0).then(|| (after - before) / before * 100.0)
+}
+
+pub fn print_comparison(
+    method: &str,
+    urls: &[ComparedUrl],
+    percentile: Percentile,
+    format: OutputFormat,
+) {
+    match format {
+        OutputFormat::Table => print_table(method, urls, percentile),
+        OutputFormat::Json => print_json(method, urls, percentile),
+        OutputFormat::Csv => print_csv(urls, percentile),
+        OutputFormat::Markdown => print_markdown(urls, percentile),
+    }
+}
+
+fn print_table(method: &str, urls: &[ComparedUrl], percentile: Percentile) {
+    let reference = &urls[0];
+
+    println!("=================================================================================");
+    println!(
+        "🏁 API latency comparison ({}) for {}",
+        percentile.label(),
+        method
+    );
+    for (i, compared) in urls.iter().enumerate() {
+        println!("{}: {}", url_label(i), compared.url);
+    }
+    println!();
+
+    print!(
+        "{:<34} {:>10}",
+        "🌎 REGION",
+        format!("{} (ms)", url_label(0))
+    );
+    for i in 1..urls.len() {
+        print!(" {:>26}", format!("{} (ms)", url_label(i)));
+    }
+    println!();
+    println!("{}", "-".repeat(45 + 27 * (urls.len() - 1)));
+
+    for (i, region) in merged_regions(urls).into_iter().enumerate() {
+        let label = format!(
+            "{} {}  {} ({})",
+            i + 1,
+            region_flag(&region.country),
+            region.country,
+            region.region
+        );
+        let reference_value = find_region(reference, &region.code).map(|r| percentile.value(r));
+        print!(
+            "{:<34} {:>10}",
+            label,
+            reference_value.map_or("-".to_string(), |v| format!("{v:.2}"))
+        );
+        for compared in &urls[1..] {
+            let cell = match (reference_value, find_region(compared, &region.code)) {
+                (Some(before), Some(r)) => format_delta(before, percentile.value(r)),
+                (None, Some(r)) => format!("{:.2}", percentile.value(r)),
+                (_, None) => "-".to_string(),
+            };
+            print!(" {:>26}", cell);
+        }
+        println!();
+    }
+
+    println!();
+    println!("=================================================================================");
+    for (i, compared) in urls.iter().enumerate().skip(1) {
+        let mut faster = 0;
+        let mut total = 0;
+        for region in &compared.regions {
+            if let Some(r) = find_region(reference, &region.code) {
+                total += 1;
+                if percentile.value(region) < percentile.value(r) {
+                    faster += 1;
+                }
+            }
+        }
+        println!(
+            "{} is faster than A in {} of {} region(s)",
+            url_label(i),
+            faster,
+            total
+        );
+    }
+    println!();
+}
+
+fn print_json(method: &str, urls: &[ComparedUrl], percentile: Percentile) {
+    let reference = &urls[0];
+    let regions: Vec<_> = merged_regions(urls)
+        .into_iter()
+        .map(|region| {
+            let reference_value =
+                find_region(reference, &region.code).map(|r| percentile.value(r));
+            let results: Vec<_> = urls
+                .iter()
+                .map(|compared| {
+                    find_region(compared, &region.code).map(|r| {
+                        let value = percentile.value(r);
+                        serde_json::json!({
+                            "url": compared.url,
+                            "amount": r.amount,
+                            "p50": r.p50,
+                            "p90": r.p90,
+                            "p99": r.p99,
+                            "delta": reference_value.map(|before| value - before),
+                            "deltaPercent": reference_value.and_then(|before| delta_percent(before, value)),
+                        })
+                    })
+                })
+                .collect();
+            serde_json::json!({
+                "code": region.code,
+                "country": region.country,
+                "region": region.region,
+                "direction": region.direction,
+                "results": results,
+            })
+        })
+        .collect();
+
+    let output = serde_json::json!({
+        "method": method,
+        "percentile": percentile.label(),
+        "urls": urls.iter().map(|u| u.url.as_str()).collect::<Vec<_>>(),
+        "regions": regions,
+    });
+    println!("{}", serde_json::to_string_pretty(&output).unwrap());
+}
+
+fn print_csv(urls: &[ComparedUrl], percentile: Percentile) {
+    let reference = &urls[0];
+    println!("code,country,region,direction,url,amount,p50,p90,p99,delta,delta_percent");
+    for region in merged_regions(urls) {
+        let reference_value = find_region(reference, &region.code).map(|r| percentile.value(r));
+        for compared in urls {
+            let Some(r) = find_region(compared, &region.code) else {
+                continue;
+            };
+            let value = percentile.value(r);
+            println!(
+                "{},{},{},{},{},{},{:.2},{:.2},{:.2},{},{}",
+                csv_field(&r.code),
+                csv_field(&r.country),
+                csv_field(&r.region),
+                csv_field(&r.direction),
+                csv_field(&compared.url),
+                r.amount,
+                r.p50,
+                r.p90,
+                r.p99,
+                reference_value.map_or(String::new(), |before| format!("{:.2}", value - before)),
+                reference_value
+                    .and_then(|before| delta_percent(before, value))
+                    .map_or(String::new(), |p| format!("{p:.1}"))
+            );
+        }
+    }
+}
+
+fn print_markdown(urls: &[ComparedUrl], percentile: Percentile) {
+    let reference = &urls[0];
+    for (i, compared) in urls.iter().enumerate() {
+        println!("- **{}**: {}", url_label(i), compared.url);
+    }
+    println!();
+
+    print!("| # | Region |");
+    for i in 0..urls.len() {
+        print!(" {} {} (ms) |", url_label(i), percentile.label());
+    }
+    println!();
+    print!("|--:|--------|");
+    for _ in urls {
+        print!("---------:|");
+    }
+    println!();
+
+    for (i, region) in merged_regions(urls).into_iter().enumerate() {
+        let reference_value = find_region(reference, &region.code).map(|r| percentile.value(r));
+        print!(
+            "| {} | {} {} ({}) |",
+            i + 1,
+            region_flag(&region.country),
+            markdown_cell(&region.country),
+            markdown_cell(&region.region)
+        );
+        for (j, compared) in urls.iter().enumerate() {
+            let cell = match (reference_value, find_region(compared, &region.code)) {
+                (Some(before), Some(r)) if j > 0 => format_delta(before, percentile.value(r)),
+                (_, Some(r)) => format!("{:.2}", percentile.value(r)),
+                (_, None) => "-".to_string(),
+            };
+            print!(" {} |", cell);
+        }
+        println!();
+    }
+}
@@ -0,0 +1,588 @@
+use std::io::Read;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+/// A request imported from a curl command line.
+#[derive(Debug, Default)]
+pub struct CurlRequest {
+    pub url: String,
+    pub method: String,
+    /// Headers in the "Key: Value" format used by `--header`.
+    pub headers: Vec<String>,
+    pub body: Option<String>,
+    /// Options that were accepted but don't apply to the measured request.
+    pub warnings: Vec<String>,
+}
+
+/// Options that change how curl prints its output or follows redirects, they are ignored with a
+/// warning since `borrow ms` measures a single request.
+const IGNORED_FLAGS: &[(&str, &str)] = &[
+    ("-s", "--silent"),
+    ("-S", "--show-error"),
+    ("-v", "--verbose"),
+    ("-i", "--include"),
+    ("-L", "--location"),
+];
+
+/// Options that take a value, as (short, long) pairs.
+const VALUE_OPTIONS: &[(&str, &str)] = &[
+    ("-X", "--request"),
+    ("-H", "--header"),
+    ("-d", "--data"),
+    ("", "--data-raw"),
+    ("", "--data-binary"),
+    ("", "--data-ascii"),
+    ("", "--data-urlencode"),
+    ("", "--json"),
+    ("-u", "--user"),
+    ("-A", "--user-agent"),
+    ("-e", "--referer"),
+    ("-b", "--cookie"),
+    ("", "--url"),
+];
+
+/// Reads a curl command from the `--from-curl` value, or from stdin when the value is "-".
+pub fn read_curl_command(value: &str) -> Result<String, String> {
+    if value != "-" {
+        return Ok(value.to_string());
+    }
+    let mut command = String::new();
+    std::io::stdin()
+        .read_to_string(&mut command)
+        .map_err(|e| format!("failed to read curl command from stdin: {e}"))?;
+    Ok(command)
+}
+
+fn read_data_file(path: &str, strip_newlines: bool) -> Result<String, String> {
+    let content = if path == "-" {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| format!("failed to read data from stdin: {e}"))?;
+        content
+    } else {
+        std::fs::read_to_string(path).map_err(|e| format!("failed to read '{path}': {e}"))?
+    };
+    Ok(if strip_newlines {
+        content.replace(['\r', '\n'], "")
+    } else {
+        content
+    })
+}
+
+/// Reads the rest of a `$'...'` string, decoding its backslash escapes like bash does.
+fn read_ansi_c_quoted(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    arg: &mut String,
+) -> Result<(), String> {
+    let unterminated = || "invalid curl command: unterminated $'...' string".to_string();
+    loop {
+        match chars.next().ok_or_else(unterminated)? {
+            '\'' => return Ok(()),
+            '\\' => {
+                let c = chars.next().ok_or_else(unterminated)?;
+                let (radix, max_digits) = match c {
+                    'x' => (16, 2),
+                    'u' => (16, 4),
+                    'U' => (16, 8),
+                    '0'..='7' => (8, 3),
+                    _ => (0, 0),
+                };
+                if radix == 0 {
+                    let decoded = match c {
+                        'a' => '\u{07}',
+                        'b' => '\u{08}',
+                        'e' | 'E' => '\u{1B}',
+                        'f' => '\u{0C}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'v' => '\u{0B}',
+                        '\\' | '\'' | '"' | '?' => c,
+                        // Unknown escapes are kept as written.
+                        _ => {
+                            arg.push('\\');
+                            c
+                        }
+                    };
+                    arg.push(decoded);
+                    continue;
+                }
+
+                let mut digits = String::new();
+                if radix == 8 {
+                    digits.push(c);
+                }
+                while digits.len() < max_digits
+                    && let Some(&d) = chars.peek()
+                    && d.is_digit(radix)
+                {
+                    digits.push(d);
+                    chars.next();
+                }
+                match u32::from_str_radix(&digits, radix)
+                    .ok()
+                    .and_then(char::from_u32)
+                {
+                    Some(decoded) => arg.push(decoded),
+                    // Not an escape after all, e.g. "\xZ", keep it as written.
+                    None => {
+                        arg.push('\\');
+                        arg.push(c);
+                        arg.push_str(&digits);
+                    }
+                }
+            }
+            c => arg.push(c),
+        }
+    }
+}
+
+/// Splits a command line into arguments like a POSIX shell, with the `$'...'` strings that
+/// browsers emit in "Copy as cURL" and backslash line continuations.
+fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => args.extend(current.take()),
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(c) => current.get_or_insert_default().push(c),
+                None => return Err("invalid curl command: it ends with a backslash".to_string()),
+            },
+            '\'' => {
+                let arg = current.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => {
+                            return Err(
+                                "invalid curl command: unterminated '...' string".to_string()
+                            );
+                        }
+                    }
+                }
+            }
+            '"' => {
+                let arg = current.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('\n') => {}
+                            Some(c @ ('$' | '`' | '"' | '\\')) => arg.push(c),
+                            Some(c) => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                            None => break,
+                        },
+                        Some(c) => arg.push(c),
+                        None => {
+                            return Err(
+                                "invalid curl command: unterminated \"...\" string".to_string()
+                            );
+                        }
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                read_ansi_c_quoted(&mut chars, current.get_or_insert_default())?;
+            }
+            c => current.get_or_insert_default().push(c),
+        }
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+/// Encodes a `--data-urlencode` value like curl: "content", "=content", "name=content",
+/// "@file" or "name@file", whichever of '=' and '@' comes first.
+fn data_urlencode(value: &str) -> Result<String, String> {
+    let Some(i) = value.find(['=', '@']) else {
+        return Ok(urlencode(value));
+    };
+    let name = &value[..i];
+    let content = if value[i..].starts_with('@') {
+        read_data_file(&value[i + 1..], false)?
+    } else {
+        value[i + 1..].to_string()
+    };
+    Ok(if name.is_empty() {
+        urlencode(&content)
+    } else {
+        format!("{name}={}", urlencode(&content))
+    })
+}
+
+fn has_header(headers: &[String], name: &str) -> bool {
+    headers.iter().any(|h| {
+        h.split_once(':')
+            .is_some_and(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+    })
+}
+
+/// Splits an argument into its option name and an attached value, if any,
+/// e.g. "--header=X: 1" or "-HX: 1".
+fn split_option(arg: &str) -> (String, Option<String>) {
+    if let Some(long) = arg.strip_prefix("--") {
+        return match long.split_once('=') {
+            Some((name, value)) => (format!("--{name}"), Some(value.to_string())),
+            None => (arg.to_string(), None),
+        };
+    }
+    if arg.len() > 2 && arg.is_char_boundary(2) {
+        let (name, rest) = arg.split_at(2);
+        let takes_value = VALUE_OPTIONS.iter().any(|(short, _)| *short == name);
+        if takes_value {
+            return (name.to_string(), Some(rest.to_string()));
+        }
+    }
+    (arg.to_string(), None)
+}
+
+/// Parses a curl command line, rejecting every option that can't be mapped onto `borrow ms`.
+pub fn parse_curl_command(command: &str) -> Result<CurlRequest, String> {
+    let mut args = split_command(command)?.into_iter();
+
+    if args.next().as_deref() != Some("curl") {
+        return Err("the command must start with 'curl'.".to_string());
+    }
+
+    let mut url: Option<String> = None;
+    let mut method: Option<String> = None;
+    let mut headers: Vec<String> = Vec::new();
+    let mut data: Vec<String> = Vec::new();
+    let mut json = false;
+    let mut head = false;
+    let mut get = false;
+    let mut compressed = false;
+    let mut ignored: Vec<String> = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            if url.replace(arg.clone()).is_some() {
+                return Err(format!("only one URL is supported, found another: '{arg}'"));
+            }
+            continue;
+        }
+
+        // Combined short flags without values, e.g. -sSL
+        if !arg.starts_with("--")
+            && arg.len() > 2
+            && arg[1..].chars().all(|c| {
+                let flag = format!("-{c}");
+                IGNORED_FLAGS.iter().any(|(short, _)| *short == flag)
+                    || flag == "-I"
+                    || flag == "-G"
+            })
+        {
+            for c in arg[1..].chars() {
+                match c {
+                    'I' => head = true,
+                    'G' => get = true,
+                    c => {
+                        let flag = format!("-{c}");
+                        if !ignored.contains(&flag) {
+                            ignored.push(flag);
+                        }
+                    }
+                }
+            }
+            continue;
+        }
+
+        let (name, attached) = split_option(&arg);
+
+        if IGNORED_FLAGS
+            .iter()
+            .any(|(short, long)| name == *short || name == *long)
+        {
+            if !ignored.contains(&name) {
+                ignored.push(name);
+            }
+            continue;
+        }
+
+        match name.as_str() {
+            "-I" | "--head" => {
+                head = true;
+                continue;
+            }
+            "-G" | "--get" => {
+                get = true;
+                continue;
+            }
+            "--compressed" => {
+                compressed = true;
+                continue;
+            }
+            _ => {}
+        }
+
+        let Some((_, long)) = VALUE_OPTIONS
+            .iter()
+            .find(|(short, long)| (!short.is_empty() && name == *short) || name == *long)
+        else {
+            return Err(format!("unsupported curl option '{name}'."));
+        };
+
+        let value = match attached {
+            Some(value) => value,
+            None => args
+                .next()
+                .ok_or_else(|| format!("curl option '{name}' requires a value."))?,
+        };
+
+        match *long {
+            "--request" => method = Some(value.to_uppercase()),
+            "--header" => {
+                if value.starts_with('@') {
+                    return Err("reading headers from a file is not supported.".to_string());
+                }
+                if !value.contains(':') {
+                    return Err(format!(
+                        "unsupported curl header '{value}', expected 'Key: Value'."
+                    ));
+                }
+                headers.push(value);
+            }
+            "--data" | "--data-ascii" => match value.strip_prefix('@') {
+                Some(path) => data.push(read_data_file(path, true)?),
+                None => data.push(value),
+            },
+            "--data-binary" => match value.strip_prefix('@') {
+                Some(path) => data.push(read_data_file(path, false)?),
+                None => data.push(value),
+            },
+            "--data-raw" => data.push(value),
+            "--data-urlencode" => data.push(data_urlencode(&value)?),
+            "--json" => {
+                json = true;
+                match value.strip_prefix('@') {
+                    Some(path) => data.push(read_data_file(path, false)?),
+                    None => data.push(value),
+                }
+            }
+            "--user" => {
+                if !value.contains(':') {
+                    return Err(
+                        "'-u' without a password would prompt for it, use 'user:password'."
+                            .to_string(),
+                    );
+                }
+                headers.push(format!("Authorization: Basic {}", STANDARD.encode(value)));
+            }
+            "--user-agent" => headers.push(format!("User-Agent: {value}")),
+            "--referer" => headers.push(format!("Referer: {value}")),
+            "--cookie" => {
+                if !value.contains('=') {
+                    return Err("reading cookies from a file is not supported.".to_string());
+                }
+                headers.push(format!("Cookie: {value}"));
+            }
+            "--url" => {
+                if url.replace(value.clone()).is_some() {
+                    return Err(format!(
+                        "only one URL is supported, found another: '{value}'"
+                    ));
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    let Some(mut url) = url else {
+        return Err("the curl command has no URL.".to_string());
+    };
+
+    let body = if data.is_empty() {
+        None
+    } else if get {
+        url.push(if url.contains('?') { '&' } else { '?' });
+        url.push_str(&data.join("&"));
+        None
+    } else if json {
+        Some(data.concat())
+    } else {
+        Some(data.join("&"))
+    };
+
+    if json {
+        if !has_header(&headers, "Content-Type") {
+            headers.push("Content-Type: application/json".to_string());
+        }
+        if !has_header(&headers, "Accept") {
+            headers.push("Accept: application/json".to_string());
+        }
+    } else if body.is_some() && !has_header(&headers, "Content-Type") {
+        headers.push("Content-Type: application/x-www-form-urlencoded".to_string());
+    }
+
+    let mut warnings = Vec::new();
+    if !ignored.is_empty() {
+        warnings.push(format!(
+            "ignoring curl option(s) {}, they don't apply to the measured request.",
+            ignored.join(", ")
+        ));
+    }
+    if compressed {
+        warnings.push(
+            "'--compressed' only sends an Accept-Encoding header, responses aren't decompressed."
+                .to_string(),
+        );
+        if !has_header(&headers, "Accept-Encoding") {
+            headers.push("Accept-Encoding: deflate, gzip, br".to_string());
+        }
+    }
+
+    let method = match (method, head, &body) {
+        (Some(method), _, _) => method,
+        (None, true, _) => "HEAD".to_string(),
+        (None, false, Some(_)) => "POST".to_string(),
+        (None, false, None) => "GET".to_string(),
+    };
+
+    Ok(CurlRequest {
+        url,
+        method,
+        headers,
+        body,
+        warnings,
+    })
+}
+
+pub fn urlencode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(command: &str) -> CurlRequest {
+        parse_curl_command(command).unwrap()
+    }
+
+    #[test]
+    fn data_defaults_to_a_form_post() {
+        let curl = parse("curl https://api.example.com/login -d 'user=a' -d pass=b");
+        assert_eq!(curl.method, "POST");
+        assert_eq!(curl.url, "https://api.example.com/login");
+        assert_eq!(curl.body.as_deref(), Some("user=a&pass=b"));
+        assert_eq!(
+            curl.headers,
+            ["Content-Type: application/x-www-form-urlencoded"]
+        );
+        assert!(curl.warnings.is_empty());
+    }
+
+    #[test]
+    fn explicit_method_and_headers() {
+        let curl = parse(
+            r#"curl -X put "https://api.example.com/items/1" -H 'Content-Type: application/json' --header="X-Id: 7" --data-raw '{"name":"@x"}'"#,
+        );
+        assert_eq!(curl.method, "PUT");
+        assert_eq!(curl.headers, ["Content-Type: application/json", "X-Id: 7"]);
+        assert_eq!(curl.body.as_deref(), Some(r#"{"name":"@x"}"#));
+    }
+
+    #[test]
+    fn get_without_data() {
+        let curl = parse("curl https://api.example.com/health");
+        assert_eq!(curl.method, "GET");
+        assert!(curl.headers.is_empty());
+        assert_eq!(curl.body, None);
+    }
+
+    #[test]
+    fn user_becomes_basic_auth() {
+        let curl = parse("curl -u admin:secret https://api.example.com");
+        assert_eq!(curl.headers, ["Authorization: Basic YWRtaW46c2VjcmV0"]);
+        assert!(parse_curl_command("curl -u admin https://api.example.com").is_err());
+    }
+
+    #[test]
+    fn line_continuations() {
+        let curl = parse(
+            "curl https://api.example.com/items \\\n  -H 'Accept: text/plain' \\\r\n  --data-raw 'a b'",
+        );
+        assert_eq!(curl.method, "POST");
+        assert_eq!(curl.headers[0], "Accept: text/plain");
+        assert_eq!(curl.body.as_deref(), Some("a b"));
+    }
+
+    #[test]
+    fn ansi_c_quoted_strings() {
+        let curl = parse(
+            r#"curl 'https://api.example.com/graphql' -H $'X-Note: it\'s' --data-raw $'{"query":"{\\n  me\\n}"}\n\x41\101\u00e9'"#,
+        );
+        assert_eq!(curl.headers[0], "X-Note: it's");
+        assert_eq!(
+            curl.body.as_deref(),
+            Some("{\"query\":\"{\\n  me\\n}\"}\nAAé")
+        );
+    }
+
+    #[test]
+    fn data_urlencode_forms() {
+        let path = std::env::temp_dir().join(format!("borrow-curl-{}.txt", std::process::id()));
+        std::fs::write(&path, "a b&c").unwrap();
+        let command = format!(
+            "curl https://api.example.com --data-urlencode 'q=x y' --data-urlencode =z/ --data-urlencode plain --data-urlencode 'file@{}'",
+            path.display()
+        );
+        let curl = parse(&command);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            curl.body.as_deref(),
+            Some("q=x%20y&z%2F&plain&file=a%20b%26c")
+        );
+    }
+
+    #[test]
+    fn get_moves_data_to_the_query() {
+        let curl = parse("curl -G https://api.example.com/search?x=1 -d q=a");
+        assert_eq!(curl.method, "GET");
+        assert_eq!(curl.url, "https://api.example.com/search?x=1&q=a");
+        assert_eq!(curl.body, None);
+    }
+
+    #[test]
+    fn ignored_flags_are_reported() {
+        let curl = parse("curl -sSL -v --compressed https://api.example.com");
+        assert_eq!(curl.warnings.len(), 2);
+        assert!(curl.warnings[0].contains("-s, -S, -L, -v"));
+        assert!(curl.warnings[1].contains("--compressed"));
+        assert_eq!(curl.headers, ["Accept-Encoding: deflate, gzip, br"]);
+
+        let curl = parse("curl -s -L -s --silent -sS https://api.example.com");
+        assert_eq!(curl.warnings.len(), 1);
+        assert!(curl.warnings[0].contains("-s, -L, --silent, -S,"));
+    }
+
+    #[test]
+    fn rejects_unsupported_commands() {
+        assert!(parse_curl_command("wget https://api.example.com").is_err());
+        assert!(parse_curl_command("curl --form a=b https://api.example.com").is_err());
+        assert!(parse_curl_command("curl https://a.example.com https://b.example.com").is_err());
+        assert!(parse_curl_command("curl -H 'unterminated").is_err());
+        assert!(parse_curl_command("curl -X").is_err());
+    }
+}
@@ -0,0 +1,661 @@
+use std::{
+    collections::BTreeMap,
+    io::IsTerminal,
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+    time::Duration,
+};
+
+use borrow_dev::MsError;
+use chrono::{DateTime, Utc};
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Cell, Paragraph, Row, Sparkline, Table, TableState, Wrap},
+};
+
+use super::{
+    MeasurementResults, PreparedRequest, RegionRow, RequestArgs, SavedMeasurement, execute_request,
+    load_saved_measurements, redact_headers, set_full_screen,
+};
+
+/// Samples kept per region for the sparklines.
+const MAX_HISTORY: usize = 120;
+/// Samples shown in the trend column of the table.
+const TREND_WIDTH: usize = 20;
+const SPARKLINE_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// What the dashboard shows about the measured request.
+#[derive(Default)]
+struct RequestInfo {
+    method: String,
+    url: String,
+    request_hash: String,
+    body_source: Option<String>,
+    headers: BTreeMap<String, String>,
+    iterations: Option<u32>,
+}
+
+/// Messages sent by the thread measuring live samples.
+enum Update {
+    Measuring,
+    Sample(SavedMeasurement),
+    /// Polling stopped after an error that retrying can't fix.
+    Stopped,
+}
+
+struct RegionState {
+    latest: RegionRow,
+    p50_history: Vec<f64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SortColumn {
+    P50,
+    P90,
+    P99,
+    Region,
+    Iterations,
+}
+
+impl SortColumn {
+    fn next(self) -> Self {
+        match self {
+            Self::P50 => Self::P90,
+            Self::P90 => Self::P99,
+            Self::P99 => Self::Region,
+            Self::Region => Self::Iterations,
+            Self::Iterations => Self::P50,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Grouping {
+    None,
+    Direction,
+    Country,
+}
+
+impl Grouping {
+    fn next(self) -> Self {
+        match self {
+            Self::None => Self::Direction,
+            Self::Direction => Self::Country,
+            Self::Country => Self::None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Direction => "direction",
+            Self::Country => "country",
+        }
+    }
+
+    fn key(self, region: &RegionRow) -> Option<&str> {
+        match self {
+            Self::None => None,
+            Self::Direction => Some(&region.direction),
+            Self::Country => Some(&region.country),
+        }
+    }
+}
+
+/// A line of the region table, either a group heading or a region.
+enum Entry<'a> {
+    Group(&'a str, Vec<&'a RegionState>),
+    Region(&'a RegionState),
+}
+
+struct Dashboard {
+    request: RequestInfo,
+    /// Where the samples come from, e.g. "live, every 1m".
+    source: String,
+    regions: BTreeMap<String, RegionState>,
+    latest: Option<MeasurementResults>,
+    samples: usize,
+    last_update: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    measuring: bool,
+    stopped: bool,
+    sort: SortColumn,
+    descending: bool,
+    grouping: Grouping,
+    selected: Option<String>,
+}
+
+impl Dashboard {
+    fn new(request: RequestInfo, source: String) -> Self {
+        Self {
+            request,
+            source,
+            regions: BTreeMap::new(),
+            latest: None,
+            samples: 0,
+            last_update: None,
+            last_error: None,
+            measuring: false,
+            stopped: false,
+            sort: SortColumn::P50,
+            descending: false,
+            grouping: Grouping::None,
+            selected: None,
+        }
+    }
+
+    fn push(&mut self, sample: SavedMeasurement) {
+        self.samples += 1;
+        self.measuring = false;
+        if sample.timestamp.is_some() {
+            self.last_update = sample.timestamp;
+        }
+        match sample.outcome {
+            Ok(data) => {
+                for region in &data.regions {
+                    let state =
+                        self.regions
+                            .entry(region.code.clone())
+                            .or_insert_with(|| RegionState {
+                                latest: region.clone(),
+                                p50_history: Vec::new(),
+                            });
+                    state.latest = region.clone();
+                    state.p50_history.push(region.p50);
+                    if state.p50_history.len() > MAX_HISTORY {
+                        state.p50_history.remove(0);
+                    }
+                }
+                self.latest = Some(data);
+                self.last_error = None;
+            }
+            Err(e) => self.last_error = Some(e),
+        }
+    }
+
+    fn update(&mut self, update: Update) {
+        match update {
+            Update::Measuring => self.measuring = true,
+            Update::Sample(sample) => self.push(sample),
+            Update::Stopped => self.stopped = true,
+        }
+    }
+
+    /// Regions in display order: by group, then by the sort column.
+    fn ordered(&self) -> Vec<&RegionState> {
+        let mut regions: Vec<&RegionState> = self.regions.values().collect();
+        regions.sort_by(|a, b| {
+            let (a, b) = (&a.latest, &b.latest);
+            let ordering = match self.sort {
+                SortColumn::P50 => a.p50.total_cmp(&b.p50),
+                SortColumn::P90 => a.p90.total_cmp(&b.p90),
+                SortColumn::P99 => a.p99.total_cmp(&b.p99),
+                SortColumn::Region => (&a.country, &a.region).cmp(&(&b.country, &b.region)),
+                SortColumn::Iterations => a.amount.cmp(&b.amount),
+            };
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        // The sort is stable, so regions keep their order within a group.
+        regions.sort_by(|a, b| {
+            self.grouping
+                .key(&a.latest)
+                .cmp(&self.grouping.key(&b.latest))
+        });
+        regions
+    }
+
+    fn entries(&self) -> Vec<Entry<'_>> {
+        let mut entries = Vec::new();
+        for region in self.ordered() {
+            if let Some(key) = self.grouping.key(&region.latest) {
+                let group = entries.iter_mut().rev().find_map(|e| match e {
+                    Entry::Group(group, members) if *group == key => Some(members),
+                    _ => None,
+                });
+                match group {
+                    Some(members) => members.push(region),
+                    None => entries.push(Entry::Group(key, vec![region])),
+                }
+            }
+            entries.push(Entry::Region(region));
+        }
+        entries
+    }
+
+    fn selected_region(&self) -> Option<&RegionState> {
+        self.selected
+            .as_ref()
+            .and_then(|code| self.regions.get(code))
+            .or_else(|| self.ordered().first().copied())
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        let ordered = self.ordered();
+        if ordered.is_empty() {
+            return;
+        }
+        let current = self
+            .selected_region()
+            .and_then(|selected| {
+                ordered
+                    .iter()
+                    .position(|r| r.latest.code == selected.latest.code)
+            })
+            .unwrap_or(0);
+        let index = current.saturating_add_signed(offset).min(ordered.len() - 1);
+        self.selected = Some(ordered[index].latest.code.clone());
+    }
+
+    /// Handles a key press, returns `false` to quit.
+    fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Home => self.move_selection(isize::MIN),
+            KeyCode::End => self.move_selection(isize::MAX),
+            KeyCode::Char('s') => self.sort = self.sort.next(),
+            KeyCode::Char('r') => self.descending = !self.descending,
+            KeyCode::Char('g') => self.grouping = self.grouping.next(),
+            _ => {}
+        }
+        true
+    }
+
+    fn render(&self, frame: &mut Frame) {
+        let [header, main, footer] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Min(10),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [table, side] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(main);
+
+        self.render_header(frame, header);
+        self.render_table(frame, table);
+        self.render_side(frame, side);
+
+        frame.render_widget(
+            Paragraph::new(
+                "↑/↓ select  s sort  r reverse  g group  q quit".to_string()
+                    + &format!("  (grouped by {})", self.grouping.label()),
+            )
+            .style(Style::new().fg(Color::DarkGray)),
+            footer,
+        );
+    }
+
+    fn render_header(&self, frame: &mut Frame, area: Rect) {
+        let mut status = vec![Span::raw(format!(
+            "{} · {} sample(s) · updated {}",
+            self.source,
+            self.samples,
+            self.last_update
+                .map_or("-".to_string(), |t| t.format("%H:%M:%S UTC").to_string())
+        ))];
+        if self.measuring {
+            status.push(Span::styled(
+                " · measuring…",
+                Style::new().fg(Color::Yellow),
+            ));
+        }
+        if self.stopped {
+            status.push(Span::styled(
+                " · polling stopped",
+                Style::new().fg(Color::Red),
+            ));
+        }
+
+        let mut lines = vec![
+            Line::from(Span::styled(
+                format!("🏁 {} {}", self.request.method, self.request.url),
+                Style::new().add_modifier(Modifier::BOLD),
+            )),
+            Line::from(status),
+        ];
+        if let Some(e) = &self.last_error {
+            lines.push(Line::from(Span::styled(
+                format!("Error: {e}"),
+                Style::new().fg(Color::Red),
+            )));
+        }
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered()), area);
+    }
+
+    fn render_table(&self, frame: &mut Frame, area: Rect) {
+        let arrow = if self.descending { " ▼" } else { " ▲" };
+        let header_cell = |label: &str, column: SortColumn| {
+            if self.sort == column {
+                format!("{label}{arrow}")
+            } else {
+                label.to_string()
+            }
+        };
+        let header = Row::new([
+            Cell::from("#"),
+            Cell::from(header_cell("REGION", SortColumn::Region)),
+            Cell::from("DIRECTION"),
+            Cell::from(header_cell("ITER", SortColumn::Iterations)),
+            Cell::from(header_cell("p50", SortColumn::P50)),
+            Cell::from(header_cell("p90", SortColumn::P90)),
+            Cell::from(header_cell("p99", SortColumn::P99)),
+            Cell::from("p50 TREND"),
+        ])
+        .style(Style::new().add_modifier(Modifier::BOLD));
+
+        let selected = self.selected_region().map(|r| r.latest.code.as_str());
+        let mut state = TableState::default();
+        let mut number = 0;
+        let rows: Vec<Row> = self
+            .entries()
+            .into_iter()
+            .enumerate()
+            .map(|(i, entry)| match entry {
+                Entry::Group(key, members) => {
+                    let mean = |percentile: fn(&RegionRow) -> f64| {
+                        let sum: f64 = members.iter().map(|r| percentile(&r.latest)).sum();
+                        format!("{:.2}", sum / members.len() as f64)
+                    };
+                    // Means of the regions in the group.
+                    Row::new([
+                        Cell::from("▸"),
+                        Cell::from(format!("{key} ({})", members.len())),
+                        Cell::from(""),
+                        Cell::from("mean"),
+                        Cell::from(mean(|r| r.p50)),
+                        Cell::from(mean(|r| r.p90)),
+                        Cell::from(mean(|r| r.p99)),
+                        Cell::from(""),
+                    ])
+                    .style(Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+                }
+                Entry::Region(region) => {
+                    number += 1;
+                    if selected == Some(region.latest.code.as_str()) {
+                        state.select(Some(i));
+                    }
+                    let row = &region.latest;
+                    Row::new([
+                        Cell::from(number.to_string()),
+                        Cell::from(format!("{} ({})", row.country, row.region)),
+                        Cell::from(row.direction.clone()),
+                        Cell::from(row.iterations_label()),
+                        Cell::from(format!("{:.2}", row.p50)),
+                        Cell::from(format!("{:.2}", row.p90)),
+                        Cell::from(format!("{:.2}", row.p99)),
+                        Cell::from(text_sparkline(&region.p50_history, TREND_WIDTH)),
+                    ])
+                }
+            })
+            .collect();
+
+        let widths = [
+            Constraint::Length(3),
+            Constraint::Min(20),
+            Constraint::Length(9),
+            Constraint::Length(7),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(TREND_WIDTH as u16),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::bordered().title(" Regions (ms) "))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+
+    fn render_side(&self, frame: &mut Frame, area: Rect) {
+        let [details, history, metadata] = Layout::vertical([
+            Constraint::Length(9),
+            Constraint::Length(7),
+            Constraint::Min(6),
+        ])
+        .areas(area);
+
+        let region = self.selected_region();
+        let details_lines = match region {
+            Some(region) => {
+                let row = &region.latest;
+                let (min, max) = region
+                    .p50_history
+                    .iter()
+                    .fold((f64::MAX, f64::MIN), |(min, max), v| {
+                        (min.min(*v), max.max(*v))
+                    });
+                vec![
+                    Line::from(format!("{} ({})", row.country, row.region)),
+                    Line::from(format!("Code: {}", row.code)),
+                    Line::from(format!("Direction: {}", row.direction)),
+                    Line::from(format!("Iterations: {}", row.iterations_label())),
+                    Line::from(format!(
+                        "p50 {:.2} · p90 {:.2} · p99 {:.2} ms",
+                        row.p50, row.p90, row.p99
+                    )),
+                    Line::from(format!(
+                        "p50 over {} sample(s): {min:.2} to {max:.2} ms",
+                        region.p50_history.len()
+                    )),
+                ]
+            }
+            None => vec![Line::from("No region measured yet.")],
+        };
+        frame.render_widget(
+            Paragraph::new(details_lines).block(Block::bordered().title(" Details ")),
+            details,
+        );
+
+        // Bars start at the lowest p50 rather than zero, so that changes stand out, and are in
+        // hundredths of a millisecond since they have to be integers.
+        let data: Vec<u64> = region
+            .map(|r| {
+                let min = r.p50_history.iter().copied().fold(f64::MAX, f64::min);
+                r.p50_history
+                    .iter()
+                    .map(|v| ((v - min) * 100.0) as u64 + 1)
+                    .collect()
+            })
+            .unwrap_or_default();
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::bordered().title(" p50 history "))
+                .data(&data[data.len().saturating_sub(history.width as usize)..])
+                .style(Style::new().fg(Color::Green)),
+            history,
+        );
+
+        let request = &self.request;
+        let mut lines = vec![
+            Line::from(format!("{} {}", request.method, request.url)),
+            Line::from(format!("Request hash: {}", request.request_hash)),
+        ];
+        if let Some(source) = &request.body_source {
+            lines.push(Line::from(format!("Body: {source}")));
+        }
+        if let Some(iterations) = request.iterations {
+            lines.push(Line::from(format!("Iterations per region: {iterations}")));
+        }
+        for (name, value) in &request.headers {
+            lines.push(Line::from(format!("{name}: {value}")));
+        }
+        if let Some(latest) = &self.latest {
+            lines.push(Line::from(""));
+            if let Some(status) = latest.status {
+                lines.push(Line::from(format!("Response status: {status}")));
+            }
+            lines.push(Line::from(format!("Regions: {}", latest.regions.len())));
+            if let Some(url) = &latest.share_url {
+                lines.push(Line::from(format!("Share URL: {url}")));
+            }
+            if let Some(url) = &latest.video_url {
+                lines.push(Line::from(format!("Video URL: {url}")));
+            }
+        }
+        frame.render_widget(
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().title(" Request ")),
+            metadata,
+        );
+    }
+}
+
+/// Renders the last `width` values as a line of block characters.
+fn text_sparkline(values: &[f64], width: usize) -> String {
+    let values = &values[values.len().saturating_sub(width)..];
+    let min = values.iter().copied().fold(f64::MAX, f64::min);
+    let max = values.iter().copied().fold(f64::MIN, f64::max);
+    values
+        .iter()
+        .map(|v| {
+            if max == min {
+                SPARKLINE_BARS[3]
+            } else {
+                SPARKLINE_BARS[((v - min) / (max - min) * 7.0).round() as usize]
+            }
+        })
+        .collect()
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    dashboard: &mut Dashboard,
+    updates: Option<Receiver<Update>>,
+) -> std::io::Result<()> {
+    loop {
+        if let Some(updates) = &updates {
+            while let Ok(update) = updates.try_recv() {
+                dashboard.update(update);
+            }
+        }
+
+        terminal.draw(|frame| dashboard.render(frame))?;
+
+        if event::poll(Duration::from_millis(250))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && !dashboard.handle_key(key.code, key.modifiers)
+        {
+            return Ok(());
+        }
+    }
+}
+
+fn show(mut dashboard: Dashboard, updates: Option<Receiver<Update>>) {
+    if !std::io::stdout().is_terminal() {
+        eprintln!("Error: the dashboard needs an interactive terminal.");
+        std::process::exit(1);
+    }
+
+    // Retry warnings would be drawn over the dashboard.
+    set_full_screen(true);
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut dashboard, updates);
+    ratatui::restore();
+    set_full_screen(false);
+
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+/// Shows the measurements saved in a file.
+pub fn open_dashboard(path: &Path) {
+    let samples = match load_saved_measurements(path) {
+        Ok(samples) if samples.is_empty() => {
+            eprintln!("Error: '{}' contains no measurement.", path.display());
+            std::process::exit(1);
+        }
+        Ok(samples) => samples,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let mut request = RequestInfo::default();
+    for sample in &samples {
+        let fields = [
+            (&mut request.method, &sample.method),
+            (&mut request.url, &sample.url),
+            (&mut request.request_hash, &sample.request_hash),
+        ];
+        for (target, value) in fields {
+            if let Some(value) = value {
+                target.clone_from(value);
+            }
+        }
+        if sample.body_source.is_some() {
+            request.body_source.clone_from(&sample.body_source);
+        }
+    }
+
+    let mut dashboard = Dashboard::new(request, format!("file {}", path.display()));
+    for sample in samples {
+        dashboard.push(sample);
+    }
+    show(dashboard, None);
+}
+
+fn poll(request: PreparedRequest, args: RequestArgs, every: Duration, updates: Sender<Update>) {
+    loop {
+        if updates.send(Update::Measuring).is_err() {
+            return;
+        }
+        let timestamp = Some(Utc::now());
+        let outcome = execute_request(&args, &request);
+        let fatal = outcome.as_ref().is_err_and(MsError::is_fatal);
+        let sample = SavedMeasurement {
+            method: None,
+            url: None,
+            request_hash: None,
+            body_source: None,
+            timestamp,
+            outcome: outcome
+                .map(|response| MeasurementResults::from_response(&response))
+                .map_err(|e| e.to_string()),
+        };
+        if updates.send(Update::Sample(sample)).is_err() {
+            return;
+        }
+        if fatal {
+            let _ = updates.send(Update::Stopped);
+            return;
+        }
+        std::thread::sleep(every);
+    }
+}
+
+/// Measures the request every `every` and shows the results as they come in.
+pub fn live_dashboard(request: PreparedRequest, args: RequestArgs, every: Duration) {
+    let info = RequestInfo {
+        method: request.method.clone(),
+        url: request.url.clone(),
+        request_hash: request.hash(),
+        body_source: request.body_source.as_ref().map(|s| s.to_string()),
+        headers: redact_headers(&request.params.measure_request.headers),
+        iterations: request.params.iterations,
+    };
+    let dashboard = Dashboard::new(
+        info,
+        format!("live, every {}", humantime::format_duration(every)),
+    );
+
+    let (sender, receiver) = mpsc::channel();
+    // The thread is left running when the dashboard is closed, the process exits right after.
+    std::thread::spawn(move || poll(request, args, every, sender));
+    show(dashboard, Some(receiver));
+}
@@ -0,0 +1,253 @@
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::Utc;
+
+use super::RegionRow;
+
+/// How long pushing to an OTLP collector may take.
+const OTLP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where `--export` sends the results.
+#[derive(Debug, Clone)]
+pub enum ExportTarget {
+    /// A file in the Prometheus text format, e.g. for the node exporter's textfile collector.
+    Prometheus(PathBuf),
+    /// The base URL of an OTLP/HTTP collector.
+    Otlp(String),
+}
+
+/// Parses `--export prometheus=<path>` or `--export otlp=<url>`.
+pub fn parse_export(value: &str) -> Result<ExportTarget, String> {
+    match value.split_once('=') {
+        Some(("prometheus", path)) if !path.is_empty() => {
+            Ok(ExportTarget::Prometheus(PathBuf::from(path)))
+        }
+        Some(("otlp", url)) if url.starts_with("http://") || url.starts_with("https://") => {
+            Ok(ExportTarget::Otlp(url.to_string()))
+        }
+        _ => Err(format!(
+            "invalid export '{value}', expected 'prometheus=<path>' or 'otlp=<http(s) URL>'"
+        )),
+    }
+}
+
+/// The results of a measured request, as exported to monitoring systems.
+pub struct ExportedRun<'a> {
+    /// Name of the scenario, for `borrow ms run`.
+    pub scenario: Option<&'a str>,
+    pub method: &'a str,
+    pub url: &'a str,
+    pub regions: &'a [RegionRow],
+}
+
+impl ExportedRun<'_> {
+    fn labels(&self) -> Vec<(&'static str, &str)> {
+        let mut labels = vec![("method", self.method), ("url", self.url)];
+        if let Some(scenario) = self.scenario {
+            labels.push(("scenario", scenario));
+        }
+        labels
+    }
+}
+
+fn region_labels(region: &RegionRow) -> [(&'static str, &str); 4] {
+    [
+        ("region", &region.code),
+        ("country", &region.country),
+        ("location", &region.region),
+        ("direction", &region.direction),
+    ]
+}
+
+fn prometheus_labels(labels: &[(&str, &str)]) -> String {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{name}=\"{value}\"")
+        })
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+/// Renders the runs in the Prometheus text exposition format.
+pub fn render_prometheus(runs: &[ExportedRun]) -> String {
+    let mut text = String::new();
+
+    text.push_str("# HELP borrow_ms_latency_seconds Latency of the API measured from a region.\n");
+    text.push_str("# TYPE borrow_ms_latency_seconds gauge\n");
+    for run in runs {
+        for region in run.regions {
+            for (quantile, value) in [
+                ("0.5", region.p50),
+                ("0.9", region.p90),
+                ("0.99", region.p99),
+            ] {
+                let mut labels = run.labels();
+                labels.extend(region_labels(region));
+                labels.push(("quantile", quantile));
+                let _ = writeln!(
+                    text,
+                    "borrow_ms_latency_seconds{} {}",
+                    prometheus_labels(&labels),
+                    value / 1000.0
+                );
+            }
+        }
+    }
+
+    text.push_str(
+        "# HELP borrow_ms_successful_requests Requests to the API that succeeded from a region.\n",
+    );
+    text.push_str("# TYPE borrow_ms_successful_requests gauge\n");
+    for run in runs {
+        for region in run.regions {
+            let mut labels = run.labels();
+            labels.extend(region_labels(region));
+            let _ = writeln!(
+                text,
+                "borrow_ms_successful_requests{} {}",
+                prometheus_labels(&labels),
+                region.amount
+            );
+        }
+    }
+
+    text.push_str("# HELP borrow_ms_last_run_timestamp_seconds When the API was last measured.\n");
+    text.push_str("# TYPE borrow_ms_last_run_timestamp_seconds gauge\n");
+    let now = Utc::now().timestamp();
+    for run in runs {
+        let _ = writeln!(
+            text,
+            "borrow_ms_last_run_timestamp_seconds{} {now}",
+            prometheus_labels(&run.labels())
+        );
+    }
+
+    text
+}
+
+fn write_prometheus(path: &Path, runs: &[ExportedRun]) -> Result<(), String> {
+    // Written next to the target and renamed, so that collectors never read a partial file.
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(".{}.tmp", std::process::id()));
+    std::fs::write(&temp, render_prometheus(runs))
+        .and_then(|_| std::fs::rename(&temp, path))
+        .map_err(|e| format!("failed to write '{}': {e}", path.display()))
+}
+
+fn otlp_attributes(labels: &[(&str, &str)]) -> serde_json::Value {
+    labels
+        .iter()
+        .map(|(key, value)| serde_json::json!({ "key": key, "value": { "stringValue": value } }))
+        .collect()
+}
+
+/// Builds an OTLP `ExportMetricsServiceRequest` in its JSON encoding.
+pub fn render_otlp(runs: &[ExportedRun]) -> serde_json::Value {
+    let time = Utc::now()
+        .timestamp_nanos_opt()
+        .unwrap_or_default()
+        .to_string();
+    let time = time.as_str();
+    let gauge = |name: &str, unit: &str, description: &str, value: fn(&RegionRow) -> f64| {
+        let points: Vec<serde_json::Value> = runs
+            .iter()
+            .flat_map(|run| {
+                run.regions.iter().map(move |region| {
+                    let mut labels = run.labels();
+                    labels.extend(region_labels(region));
+                    serde_json::json!({
+                        "timeUnixNano": time,
+                        "asDouble": value(region),
+                        "attributes": otlp_attributes(&labels),
+                    })
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "name": name,
+            "unit": unit,
+            "description": description,
+            "gauge": { "dataPoints": points },
+        })
+    };
+
+    let metrics = [
+        gauge(
+            "borrow.ms.latency.p50",
+            "ms",
+            "50th percentile latency of the API measured from a region",
+            |r| r.p50,
+        ),
+        gauge(
+            "borrow.ms.latency.p90",
+            "ms",
+            "90th percentile latency of the API measured from a region",
+            |r| r.p90,
+        ),
+        gauge(
+            "borrow.ms.latency.p99",
+            "ms",
+            "99th percentile latency of the API measured from a region",
+            |r| r.p99,
+        ),
+        gauge(
+            "borrow.ms.amount",
+            "{request}",
+            "Requests to the API that succeeded from a region",
+            |r| r.amount as f64,
+        ),
+    ];
+
+    serde_json::json!({
+        "resourceMetrics": [{
+            "resource": {
+                "attributes": otlp_attributes(&[("service.name", "borrow-ms")]),
+            },
+            "scopeMetrics": [{
+                "scope": { "name": "borrow-ms", "version": env!("CARGO_PKG_VERSION") },
+                "metrics": metrics,
+            }],
+        }],
+    })
+}
+
+fn push_otlp(endpoint: &str, runs: &[ExportedRun]) -> Result<(), String> {
+    let endpoint = endpoint.trim_end_matches('/');
+    let url = if endpoint.ends_with("/v1/metrics") {
+        endpoint.to_string()
+    } else {
+        format!("{endpoint}/v1/metrics")
+    };
+
+    let response = reqwest::blocking::Client::new()
+        .post(&url)
+        .timeout(OTLP_TIMEOUT)
+        .json(&render_otlp(runs))
+        .send()
+        .map_err(|e| format!("failed to push metrics to {url}: {e}"))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "failed to push metrics to {url}: {} {}",
+            response.status(),
+            response.text().unwrap_or_default()
+        ));
+    }
+    Ok(())
+}
+
+/// Sends the runs to an export target.
+pub fn export_results(target: &ExportTarget, runs: &[ExportedRun]) -> Result<(), String> {
+    match target {
+        ExportTarget::Prometheus(path) => write_prometheus(path, runs),
+        ExportTarget::Otlp(endpoint) => push_otlp(endpoint, runs),
+    }
+}
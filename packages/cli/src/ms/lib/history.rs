@@ -0,0 +1,248 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::PathBuf,
+};
+
+use borrow_dev::{MeasureResponse, get_root_data_dir};
+use chrono::{DateTime, Utc};
+
+use super::{RegionRow, parse_regions, region_flag};
+
+fn get_data_dir() -> PathBuf {
+    get_root_data_dir().join("ms")
+}
+
+pub fn get_history_dir() -> PathBuf {
+    get_data_dir().join("history")
+}
+
+/// A measurement persisted under `get_history_dir()`.
+pub struct HistoryRecord {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub method: String,
+    pub url: String,
+    pub request_hash: String,
+    pub response: MeasureResponse,
+}
+
+impl HistoryRecord {
+    pub fn new(method: &str, url: &str, request_hash: &str, response: MeasureResponse) -> Self {
+        let timestamp = Utc::now();
+        Self {
+            id: format!(
+                "{}-{}",
+                timestamp.format("%Y%m%d-%H%M%S%3f"),
+                &request_hash[..8.min(request_hash.len())]
+            ),
+            timestamp,
+            method: method.to_string(),
+            url: url.to_string(),
+            request_hash: request_hash.to_string(),
+            response,
+        }
+    }
+
+    pub fn regions(&self) -> Vec<RegionRow> {
+        parse_regions(&self.response)
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "timestamp": self.timestamp.to_rfc3339(),
+            "method": self.method,
+            "url": self.url,
+            "requestHash": self.request_hash,
+            "response": serde_json::to_value(&self.response).unwrap(),
+        })
+    }
+
+    fn from_json(value: serde_json::Value) -> Option<Self> {
+        Some(Self {
+            id: value["id"].as_str()?.to_string(),
+            timestamp: DateTime::parse_from_rfc3339(value["timestamp"].as_str()?)
+                .ok()?
+                .with_timezone(&Utc),
+            method: value["method"].as_str()?.to_string(),
+            url: value["url"].as_str()?.to_string(),
+            request_hash: value["requestHash"].as_str()?.to_string(),
+            response: serde_json::from_value(value["response"].clone()).ok()?,
+        })
+    }
+}
+
+/// Writes the record to the history directory and returns its path.
+///
+/// Records never overwrite each other: when the ID is taken, e.g. by a concurrent run in the
+/// same millisecond, a counter is appended to it.
+pub fn save_history_record(record: &mut HistoryRecord) -> std::io::Result<PathBuf> {
+    let dir = get_history_dir();
+    fs::create_dir_all(&dir)?;
+    let base_id = record.id.clone();
+    for attempt in 1.. {
+        let path = dir.join(format!("{}.json", record.id));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                let content = serde_json::to_string_pretty(&record.to_json()).unwrap();
+                file.write_all(content.as_bytes())?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                record.id = format!("{base_id}-{attempt}");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!()
+}
+
+/// Loads every readable record, oldest first. Files that can't be parsed are skipped.
+pub fn load_history() -> Vec<HistoryRecord> {
+    let Ok(entries) = fs::read_dir(get_history_dir()) else {
+        return Vec::new();
+    };
+
+    let mut records: Vec<HistoryRecord> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .filter_map(HistoryRecord::from_json)
+        .collect();
+
+    records.sort_by_key(|r| r.timestamp);
+    records
+}
+
+/// Finds a record by its full ID or by an unambiguous ID prefix.
+pub fn find_history_record(id: &str) -> Result<HistoryRecord, String> {
+    let mut matches: Vec<HistoryRecord> = load_history()
+        .into_iter()
+        .filter(|r| r.id.starts_with(id))
+        .collect();
+
+    if let Some(index) = matches.iter().position(|r| r.id == id) {
+        return Ok(matches.swap_remove(index));
+    }
+
+    match matches.len() {
+        0 => Err(format!(
+            "no run found with ID '{id}'. Run 'borrow ms history' to list past runs."
+        )),
+        1 => Ok(matches.remove(0)),
+        n => Err(format!("run ID '{id}' is ambiguous, it matches {n} runs.")),
+    }
+}
+
+pub fn print_history(records: &[HistoryRecord]) {
+    if records.is_empty() {
+        println!("No runs recorded yet.");
+        return;
+    }
+
+    println!(
+        "{:<27} {:<20} {:<7} {:>7} {:>10} {:>10}  URL",
+        "ID", "DATE (UTC)", "METHOD", "REGIONS", "BEST p50", "WORST p99"
+    );
+    println!("{}", "-".repeat(107));
+    for record in records {
+        let regions = record.regions();
+        let best_p50 = regions.iter().map(|r| r.p50).reduce(f64::min);
+        let worst_p99 = regions.iter().map(|r| r.p99).reduce(f64::max);
+        println!(
+            "{:<27} {:<20} {:<7} {:>7} {:>10} {:>10}  {}",
+            record.id,
+            record.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            record.method,
+            regions.len(),
+            best_p50.map_or("-".to_string(), |v| format!("{v:.2}")),
+            worst_p99.map_or("-".to_string(), |v| format!("{v:.2}")),
+            record.url
+        );
+    }
+}
+
+/// Formats `after` with its absolute and relative change from `before`, e.g. "130.00 (+10.00, +8.3%)".
+pub fn format_delta(before: f64, after: f64) -> String {
+    let delta = after - before;
+    if before == 0.0 {
+        return format!("{after:.2} ({delta:+.2})");
+    }
+    let percent = delta / before * 100.0;
+    format!("{after:.2} ({delta:+.2}, {percent:+.1}%)")
+}
+
+pub fn print_diff(a: &HistoryRecord, b: &HistoryRecord) {
+    let regions_a = a.regions();
+    let regions_b = b.regions();
+
+    println!("=================================================================================");
+    println!(
+        "A: {} {} {} ({})",
+        a.id,
+        a.method,
+        a.url,
+        a.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    println!(
+        "B: {} {} {} ({})",
+        b.id,
+        b.method,
+        b.url,
+        b.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    println!();
+    println!(
+        "{:<34} {:>26} {:>26} {:>26}",
+        "🌎 REGION", "p50 (ms)", "p90 (ms)", "p99 (ms)"
+    );
+    println!("{}", "-".repeat(115));
+
+    let mut i = 0;
+    for region_b in &regions_b {
+        let Some(region_a) = regions_a.iter().find(|r| r.code == region_b.code) else {
+            continue;
+        };
+        i += 1;
+        let label = format!(
+            "{} {}  {} ({})",
+            i,
+            region_flag(&region_b.country),
+            region_b.country,
+            region_b.region
+        );
+        println!(
+            "{:<34} {:>26} {:>26} {:>26}",
+            label,
+            format_delta(region_a.p50, region_b.p50),
+            format_delta(region_a.p90, region_b.p90),
+            format_delta(region_a.p99, region_b.p99)
+        );
+    }
+
+    let only_a: Vec<&str> = regions_a
+        .iter()
+        .filter(|r| !regions_b.iter().any(|other| other.code == r.code))
+        .map(|r| r.code.as_str())
+        .collect();
+    let only_b: Vec<&str> = regions_b
+        .iter()
+        .filter(|r| !regions_a.iter().any(|other| other.code == r.code))
+        .map(|r| r.code.as_str())
+        .collect();
+
+    if !only_a.is_empty() || !only_b.is_empty() {
+        println!();
+    }
+    if !only_a.is_empty() {
+        println!("Only in A: {}", only_a.join(", "));
+    }
+    if !only_b.is_empty() {
+        println!("Only in B: {}", only_b.join(", "));
+    }
+
+    println!();
+    println!("=================================================================================");
+}
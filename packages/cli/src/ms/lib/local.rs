@@ -0,0 +1,113 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use borrow_dev::{LatencyStats, MeasureResponse, MsError, RegionLatency, RegionMetadata};
+
+use super::{PreparedRequest, summarize_error};
+
+/// Region code used for measurements made from the current machine.
+pub const LOCAL_REGION: &str = "local";
+
+/// Same default as the `ITERATIONS` variable of the ms host.
+pub const LOCAL_ITERATIONS: u32 = 10;
+
+/// Nearest-rank percentile, matching `packages/ms/src/host/worker.ts`.
+pub fn percentile(sorted_latencies: &[f64], p: f64) -> f64 {
+    if sorted_latencies.is_empty() {
+        return 0.0;
+    }
+    let idx = (sorted_latencies.len() as f64 * (p / 100.0)).ceil() as isize - 1;
+    (sorted_latencies[idx.max(0) as usize] * 100.0).round() / 100.0
+}
+
+/// Runs the measure request from this machine and returns a response shaped like the ms API's.
+pub fn measure_locally(
+    request: &PreparedRequest,
+    iterations: u32,
+    timeout: Duration,
+) -> Result<MeasureResponse, MsError> {
+    let measure_request = &request.params.measure_request;
+    let method = reqwest::Method::from_bytes(request.method.as_bytes())
+        .map_err(|e| MsError::InvalidParams(format!("invalid method: {e}")))?;
+
+    let client = reqwest::blocking::Client::new();
+    let mut latencies: Vec<f64> = Vec::with_capacity(iterations as usize);
+    let mut amount = 0;
+    let mut responses: Vec<String> = Vec::new();
+    // An error status that retrying the measurement won't fix, unlike connection failures.
+    let mut rejected_status: Option<u16> = None;
+    // The timeout applies to all the iterations together, like for the ms API.
+    let deadline = Instant::now() + timeout;
+
+    for _ in 0..iterations {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(MsError::Timeout(timeout));
+        }
+        let mut builder = client
+            .request(method.clone(), &request.url)
+            .timeout(remaining);
+        for (key, value) in &measure_request.headers {
+            builder = builder.header(key, value);
+        }
+        if let Some(body) = &measure_request.body {
+            builder = builder.body(body.clone());
+        }
+
+        let start = Instant::now();
+        match builder.send() {
+            Ok(res) => {
+                let status = res.status();
+                if status.is_success() {
+                    amount += 1;
+                } else if !matches!(status.as_u16(), 429 | 502..=504) {
+                    rejected_status.get_or_insert(status.as_u16());
+                }
+                let text = res.text().unwrap_or_default();
+                responses.push(format!("{} {}", status.as_u16(), summarize_error(&text)));
+            }
+            Err(e) if e.is_timeout() && Instant::now() >= deadline => {
+                return Err(MsError::Timeout(timeout));
+            }
+            // Request failed, don't count as successful
+            Err(e) => responses.push(format!("-1 {}", summarize_error(&e.to_string()))),
+        }
+        latencies.push(start.elapsed().as_secs_f64() * 1000.0);
+    }
+
+    if amount == 0 {
+        let message = format!("Upstream error: {}", responses.join(", "));
+        return Err(match rejected_status {
+            Some(status) => MsError::UpstreamStatus { status, message },
+            None => MsError::Upstream(message),
+        });
+    }
+
+    latencies.sort_by(|a, b| a.total_cmp(b));
+
+    let region = RegionLatency {
+        metadata: RegionMetadata {
+            country: "--".to_string(),
+            region: "Local machine".to_string(),
+            direction: "local".to_string(),
+        },
+        data: LatencyStats {
+            amount,
+            iterations: Some(u64::from(iterations)),
+            assertion_failures: None,
+            first_assertion_failure: None,
+            p50: percentile(&latencies, 50.0),
+            p90: percentile(&latencies, 90.0),
+            p99: percentile(&latencies, 99.0),
+        },
+    };
+
+    Ok(MeasureResponse {
+        status: 200,
+        latency: BTreeMap::from([(LOCAL_REGION.to_string(), region)]),
+        url: None,
+        video_url: None,
+    })
+}
@@ -0,0 +1,45 @@
+mod assertions;
+mod baseline;
+mod batch;
+mod body;
+mod breakdown;
+mod ci;
+mod compare;
+mod curl;
+mod dashboard;
+mod export;
+mod history;
+mod local;
+mod output;
+mod progress;
+mod regions;
+mod report;
+mod request;
+mod saved;
+mod scenario;
+mod thresholds;
+mod video;
+mod watch;
+
+pub use assertions::*;
+pub use baseline::*;
+pub use batch::*;
+pub use body::*;
+pub use breakdown::*;
+pub use ci::*;
+pub use compare::*;
+pub use curl::*;
+pub use dashboard::*;
+pub use export::*;
+pub use history::*;
+pub use local::*;
+pub use output::*;
+pub use progress::*;
+pub use regions::*;
+pub use report::*;
+pub use request::*;
+pub use saved::*;
+pub use scenario::*;
+pub use thresholds::*;
+pub use video::*;
+pub use watch::*;
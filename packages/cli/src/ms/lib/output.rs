@@ -0,0 +1,471 @@
+use borrow_dev::MeasureResponse;
+use clap::ValueEnum;
+use emojic::country_flag;
+
+use super::Breakdown;
+
+/// Flag emoji for a country code, or a computer for anything that isn't one (e.g. local runs).
+pub fn region_flag(country: &str) -> String {
+    if country.len() == 2 && country.chars().all(|c| c.is_ascii_alphabetic()) {
+        country_flag(country)
+    } else {
+        "💻".to_string()
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable table with banner and footer
+    Table,
+    /// A single JSON document
+    Json,
+    /// One CSV row per region, with a header row
+    Csv,
+    /// A Markdown table, followed by the share and video links
+    Markdown,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Percentile {
+    P50,
+    P90,
+    P99,
+}
+
+impl Percentile {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Percentile::P50 => "p50",
+            Percentile::P90 => "p90",
+            Percentile::P99 => "p99",
+        }
+    }
+
+    pub fn value(&self, region: &RegionRow) -> f64 {
+        match self {
+            Percentile::P50 => region.p50,
+            Percentile::P90 => region.p90,
+            Percentile::P99 => region.p99,
+        }
+    }
+}
+
+/// A single region entry of the `latency` object returned by the ms API.
+#[derive(Debug, Clone)]
+pub struct RegionRow {
+    pub code: String,
+    pub country: String,
+    pub region: String,
+    pub direction: String,
+    /// Successful requests.
+    pub amount: u64,
+    /// Attempted requests, when known.
+    pub iterations: Option<u64>,
+    /// Responses that failed the assertions, when the request has any.
+    pub assertion_failures: Option<u64>,
+    /// Why the first of these responses failed.
+    pub first_assertion_failure: Option<String>,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+impl RegionRow {
+    /// Successful out of attempted requests, e.g. "9/10".
+    pub fn iterations_label(&self) -> String {
+        match self.iterations {
+            Some(iterations) => format!("{}/{}", self.amount, iterations),
+            None => self.amount.to_string(),
+        }
+    }
+
+    /// Responses that failed the assertions out of attempted requests, e.g. "2/10".
+    pub fn assertion_failures_label(&self) -> String {
+        let failures = self.assertion_failures.unwrap_or_default();
+        match self.iterations {
+            Some(iterations) => format!("{failures}/{iterations}"),
+            None => failures.to_string(),
+        }
+    }
+}
+
+/// The regions where some responses failed the assertions.
+fn failed_assertion_regions(regions: &[RegionRow]) -> impl Iterator<Item = (usize, &RegionRow)> {
+    regions
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.assertion_failures.is_some_and(|f| f > 0))
+}
+
+pub struct MsReport {
+    pub method: String,
+    pub url: String,
+    pub request_hash: String,
+    /// Where the body came from, e.g. "file payload.json".
+    pub body_source: Option<String>,
+    pub regions: Vec<RegionRow>,
+    pub share_url: Option<String>,
+    pub video_url: Option<String>,
+    /// Phase timings from this machine, with `--breakdown`.
+    pub breakdown: Option<Breakdown>,
+}
+
+/// Extracts the regions of a response, sorted by p50 ascending.
+pub fn parse_regions(response: &MeasureResponse) -> Vec<RegionRow> {
+    let mut regions: Vec<RegionRow> = response
+        .latency
+        .iter()
+        .map(|(code, entry)| RegionRow {
+            code: code.clone(),
+            country: entry.metadata.country.clone(),
+            region: entry.metadata.region.clone(),
+            direction: entry.metadata.direction.clone(),
+            amount: entry.data.amount,
+            iterations: entry.data.iterations,
+            assertion_failures: entry.data.assertion_failures,
+            first_assertion_failure: entry.data.first_assertion_failure.clone(),
+            p50: entry.data.p50,
+            p90: entry.data.p90,
+            p99: entry.data.p99,
+        })
+        .collect();
+    regions.sort_by(|a, b| a.p50.total_cmp(&b.p50));
+    regions
+}
+
+pub fn print_report(report: &MsReport, format: OutputFormat, verbose: bool) {
+    match format {
+        OutputFormat::Table => print_table(report, verbose),
+        OutputFormat::Json => print_json(report),
+        OutputFormat::Csv => print_csv(report),
+        OutputFormat::Markdown => print_markdown(report),
+    }
+}
+
+fn print_table(report: &MsReport, verbose: bool) {
+    println!("=================================================================================");
+    println!("🏁 API latency stats for {} {}", report.method, report.url);
+    if verbose {
+        match &report.body_source {
+            Some(source) => println!(
+                "Request body hash: {} (body: {source})",
+                report.request_hash
+            ),
+            None => println!("Request body hash: {}", report.request_hash),
+        }
+    }
+    println!();
+
+    let regions = &report.regions;
+    if verbose {
+        println!(
+            "{:<34} {:<26} {:>10} {:>10} {:>10} {:>10}",
+            "🌎 REGION", "CODE", "ITERATIONS", "p50 (ms)", "p90 (ms)", "p99 (ms)"
+        );
+        println!("{}", "-".repeat(104));
+    } else {
+        println!(
+            "{:<34} {:>10} {:>10} {:>10} {:>10}",
+            "🌎 REGION", "ITERATIONS", "p50 (ms)", "p90 (ms)", "p99 (ms)"
+        );
+        println!("{}", "-".repeat(78));
+    }
+
+    for (i, region) in regions.iter().enumerate() {
+        let flag = region_flag(&region.country);
+        let label = format!("{} {}  {} ({})", i + 1, flag, region.country, region.region);
+
+        if verbose {
+            println!(
+                "{:<34} {:<26} {:>10} {:>10.2} {:>10.2} {:>10.2}",
+                label,
+                region.code,
+                region.iterations_label(),
+                region.p50,
+                region.p90,
+                region.p99
+            );
+        } else {
+            println!(
+                "{:<34} {:>10} {:>10.2} {:>10.2} {:>10.2}",
+                label,
+                region.iterations_label(),
+                region.p50,
+                region.p90,
+                region.p99
+            );
+        }
+    }
+
+    if failed_assertion_regions(regions).next().is_some() {
+        println!();
+        println!("🧪 Responses that failed the assertions");
+        println!("{:<34} {:>10}  FIRST FAILURE", "REGION", "FAILED");
+        println!("{}", "-".repeat(78));
+        for (i, region) in failed_assertion_regions(regions) {
+            let flag = region_flag(&region.country);
+            let label = format!("{} {}  {} ({})", i + 1, flag, region.country, region.region);
+            println!(
+                "{:<34} {:>10}  {}",
+                label,
+                region.assertion_failures_label(),
+                region.first_assertion_failure.as_deref().unwrap_or("-")
+            );
+        }
+    }
+
+    if let Some(breakdown) = &report.breakdown {
+        println!();
+        println!(
+            "⏱️  Phases from this machine ({}/{} successful)",
+            breakdown.amount, breakdown.iterations
+        );
+        println!(
+            "{:<34} {:>10} {:>10} {:>10} {:>10}",
+            "PHASE", "", "p50 (ms)", "p90 (ms)", "p99 (ms)"
+        );
+        println!("{}", "-".repeat(78));
+        for phase in &breakdown.phases {
+            println!(
+                "{:<34} {:>10} {:>10.2} {:>10.2} {:>10.2}",
+                phase.phase, "", phase.p50, phase.p90, phase.p99
+            );
+        }
+    }
+
+    println!();
+    println!("=================================================================================");
+
+    let mut has_extra_data = false;
+
+    if let Some(url) = &report.share_url {
+        println!("🏎️  Share the results: {url}");
+        has_extra_data = true;
+    }
+
+    if let Some(video_url) = &report.video_url {
+        println!("🎬 Share the video: {}", video_url);
+        has_extra_data = true;
+    }
+
+    if has_extra_data {
+        println!(
+            "================================================================================="
+        );
+    }
+
+    println!("❤️  By Borrow.dev \u{21C0} Open-Source Tools for Web Developers");
+    println!();
+}
+
+pub fn region_to_json(region: &RegionRow) -> serde_json::Value {
+    let mut value = serde_json::json!({
+        "code": region.code,
+        "country": region.country,
+        "region": region.region,
+        "direction": region.direction,
+        "amount": region.amount,
+        "iterations": region.iterations,
+        "p50": region.p50,
+        "p90": region.p90,
+        "p99": region.p99,
+    });
+    if let Some(failures) = region.assertion_failures {
+        value["assertionFailures"] = failures.into();
+        value["firstAssertionFailure"] = region.first_assertion_failure.clone().into();
+    }
+    value
+}
+
+/// Reads a region written by `region_to_json`.
+pub fn region_from_json(value: &serde_json::Value) -> Option<RegionRow> {
+    Some(RegionRow {
+        code: value["code"].as_str()?.to_string(),
+        country: value["country"].as_str()?.to_string(),
+        region: value["region"].as_str()?.to_string(),
+        direction: value["direction"].as_str()?.to_string(),
+        amount: value["amount"].as_u64()?,
+        iterations: value["iterations"].as_u64(),
+        assertion_failures: value["assertionFailures"].as_u64(),
+        first_assertion_failure: value["firstAssertionFailure"].as_str().map(str::to_string),
+        p50: value["p50"].as_f64()?,
+        p90: value["p90"].as_f64()?,
+        p99: value["p99"].as_f64()?,
+    })
+}
+
+pub fn breakdown_to_json(breakdown: &Breakdown) -> serde_json::Value {
+    serde_json::json!({
+        "iterations": breakdown.iterations,
+        "amount": breakdown.amount,
+        "phases": breakdown
+            .phases
+            .iter()
+            .map(|phase| {
+                serde_json::json!({
+                    "phase": phase.phase,
+                    "p50": phase.p50,
+                    "p90": phase.p90,
+                    "p99": phase.p99,
+                })
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// The `-o json` document of a report.
+pub fn report_to_json(report: &MsReport) -> serde_json::Value {
+    let regions = &report.regions;
+    let mut output = serde_json::json!({
+        "method": report.method,
+        "url": report.url,
+        "requestHash": report.request_hash,
+        "bodySource": report.body_source,
+        "regions": regions.iter().map(region_to_json).collect::<Vec<_>>(),
+        "shareUrl": report.share_url,
+        "videoUrl": report.video_url,
+    });
+    if let Some(breakdown) = &report.breakdown {
+        output["breakdown"] = breakdown_to_json(breakdown);
+    }
+    output
+}
+
+fn print_json(report: &MsReport) {
+    let output = report_to_json(report);
+    println!("{}", serde_json::to_string_pretty(&output).unwrap());
+}
+
+/// Longest error message shown in single-line outputs such as CSV cells.
+const MAX_SUMMARY_CHARS: usize = 200;
+
+/// Shortens an error message to its first line, e.g. an upstream error that embeds an HTML page.
+pub fn summarize_error(message: &str) -> String {
+    let mut lines = message.lines().map(str::trim).filter(|l| !l.is_empty());
+    let line = lines.next().unwrap_or_default();
+    if line.chars().count() > MAX_SUMMARY_CHARS {
+        let short: String = line.chars().take(MAX_SUMMARY_CHARS).collect();
+        format!("{}…", short.trim_end())
+    } else if lines.next().is_some() {
+        format!("{line} …")
+    } else {
+        line.to_string()
+    }
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn print_csv(report: &MsReport) {
+    // The assertion columns are always there, empty when the request has no assertions.
+    println!(
+        "code,country,region,direction,amount,iterations,p50,p90,p99,assertionFailures,firstAssertionFailure"
+    );
+    for region in &report.regions {
+        println!(
+            "{},{},{},{},{},{},{:.2},{:.2},{:.2},{},{}",
+            csv_field(&region.code),
+            csv_field(&region.country),
+            csv_field(&region.region),
+            csv_field(&region.direction),
+            region.amount,
+            region
+                .iterations
+                .map(|iterations| iterations.to_string())
+                .unwrap_or_default(),
+            region.p50,
+            region.p90,
+            region.p99,
+            region
+                .assertion_failures
+                .map(|failures| failures.to_string())
+                .unwrap_or_default(),
+            csv_field(
+                region
+                    .first_assertion_failure
+                    .as_deref()
+                    .unwrap_or_default()
+            )
+        );
+    }
+
+    // Keep stdout a valid CSV document, the links go to stderr instead.
+    if report.breakdown.is_some() {
+        eprintln!("The phase breakdown is only included in the table, JSON and Markdown outputs.");
+    }
+    if let Some(url) = &report.share_url {
+        eprintln!("Share the results: {url}");
+    }
+    if let Some(video_url) = &report.video_url {
+        eprintln!("Share the video: {video_url}");
+    }
+}
+
+pub fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|")
+}
+
+fn print_markdown(report: &MsReport) {
+    let regions = &report.regions;
+    println!("| # | Region | Code | Direction | Iterations | p50 (ms) | p90 (ms) | p99 (ms) |");
+    println!("|--:|--------|------|-----------|-----------:|---------:|---------:|---------:|");
+    for (i, region) in regions.iter().enumerate() {
+        println!(
+            "| {} | {} {} ({}) | `{}` | {} | {} | {:.2} | {:.2} | {:.2} |",
+            i + 1,
+            region_flag(&region.country),
+            markdown_cell(&region.country),
+            markdown_cell(&region.region),
+            region.code,
+            markdown_cell(&region.direction),
+            region.iterations_label(),
+            region.p50,
+            region.p90,
+            region.p99
+        );
+    }
+
+    if failed_assertion_regions(regions).next().is_some() {
+        println!();
+        println!("| # | Region | Failed assertions | First failure |");
+        println!("|--:|--------|------------------:|---------------|");
+        for (i, region) in failed_assertion_regions(regions) {
+            println!(
+                "| {} | {} {} ({}) | {} | {} |",
+                i + 1,
+                region_flag(&region.country),
+                markdown_cell(&region.country),
+                markdown_cell(&region.region),
+                region.assertion_failures_label(),
+                markdown_cell(region.first_assertion_failure.as_deref().unwrap_or("-"))
+            );
+        }
+    }
+
+    if let Some(breakdown) = &report.breakdown {
+        println!();
+        println!("| Phase | p50 (ms) | p90 (ms) | p99 (ms) |");
+        println!("|-------|---------:|---------:|---------:|");
+        for phase in &breakdown.phases {
+            println!(
+                "| {} | {:.2} | {:.2} | {:.2} |",
+                phase.phase, phase.p50, phase.p90, phase.p99
+            );
+        }
+    }
+
+    if report.share_url.is_some() || report.video_url.is_some() {
+        println!();
+    }
+    if let Some(url) = &report.share_url {
+        println!("- [Share the results]({url})");
+    }
+    if let Some(video_url) = &report.video_url {
+        println!("- [Share the video]({video_url})");
+    }
+}
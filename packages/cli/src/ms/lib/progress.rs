@@ -0,0 +1,101 @@
+use std::{
+    io::IsTerminal,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Exit code used when a run is cancelled with Ctrl-C (128 + SIGINT, like shells).
+pub const CANCELLED_EXIT_CODE: i32 = 130;
+
+const SPINNER_FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+static IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+static FULL_SCREEN: AtomicBool = AtomicBool::new(false);
+
+fn clear_line() {
+    if std::io::stderr().is_terminal() {
+        eprint!("\r\x1b[2K");
+    }
+}
+
+fn cancel() -> ! {
+    clear_line();
+    eprintln!("Cancelled.");
+    std::process::exit(CANCELLED_EXIT_CODE);
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{secs}s")
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+/// Makes Ctrl-C stop the running measurement and exit with `CANCELLED_EXIT_CODE`.
+pub fn install_cancel_handler() {
+    let result = ctrlc::set_handler(|| {
+        if IN_PROGRESS.load(Ordering::SeqCst) {
+            // The spinner loop stops on its next frame.
+            CANCELLED.store(true, Ordering::SeqCst);
+        } else {
+            cancel();
+        }
+    });
+    if let Err(e) = result {
+        eprintln!("Warning: failed to install the Ctrl-C handler: {e}");
+    }
+}
+
+/// Runs the task on another thread, showing a spinner with the elapsed time on stderr until it finishes.
+pub fn with_progress<T: Send>(message: &str, task: impl FnOnce() -> T + Send) -> T {
+    let show = std::io::stderr().is_terminal();
+    let start = Instant::now();
+    IN_PROGRESS.store(true, Ordering::SeqCst);
+
+    let result = std::thread::scope(|s| {
+        let handle = s.spawn(task);
+        let mut frame = 0;
+        while !handle.is_finished() {
+            if CANCELLED.load(Ordering::SeqCst) {
+                cancel();
+            }
+            if show {
+                eprint!(
+                    "\r\x1b[2K{} {} {}",
+                    SPINNER_FRAMES[frame % SPINNER_FRAMES.len()],
+                    message,
+                    format_elapsed(start.elapsed())
+                );
+                frame += 1;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        handle
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e))
+    });
+
+    IN_PROGRESS.store(false, Ordering::SeqCst);
+    clear_line();
+    result
+}
+
+/// Stops `progress_line` from printing while a full-screen UI owns the terminal.
+pub fn set_full_screen(enabled: bool) {
+    FULL_SCREEN.store(enabled, Ordering::SeqCst);
+}
+
+/// Prints a line to stderr above the spinner.
+pub fn progress_line(line: &str) {
+    if FULL_SCREEN.load(Ordering::SeqCst) {
+        return;
+    }
+    if std::io::stderr().is_terminal() {
+        eprint!("\r\x1b[2K{line}\n");
+    } else {
+        eprintln!("{line}");
+    }
+}
@@ -0,0 +1,171 @@
+use clap::Args;
+
+use super::{OutputFormat, csv_field, markdown_cell, region_flag};
+
+/// Copy of `packages/data/cloudflare-workers-placement-regions.json`, keep both in sync.
+static PLACEMENT_REGIONS: &str = include_str!("cloudflare-workers-placement-regions.json");
+
+#[derive(Debug, Clone)]
+pub struct PlacementRegion {
+    pub code: String,
+    pub country: String,
+    pub region: String,
+    pub direction: String,
+}
+
+/// All regions the ms host can measure from, in catalog order.
+pub fn placement_regions() -> Vec<PlacementRegion> {
+    let catalog: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(PLACEMENT_REGIONS).expect("Invalid placement regions catalog");
+
+    catalog
+        .into_iter()
+        .map(|(code, entry)| PlacementRegion {
+            code,
+            country: entry["country"].as_str().unwrap_or("??").to_string(),
+            region: entry["region"].as_str().unwrap_or("Unknown").to_string(),
+            direction: entry["direction"].as_str().unwrap_or("unknown").to_string(),
+        })
+        .collect()
+}
+
+#[derive(Args, Debug, Default, Clone)]
+pub struct RegionFilter {
+    /// Only measure from these regions (comma-separated region codes, see 'borrow ms regions')
+    #[arg(long = "regions", value_delimiter = ',')]
+    pub regions: Vec<String>,
+
+    /// Never measure from these regions (comma-separated region codes)
+    #[arg(long = "exclude-regions", value_delimiter = ',')]
+    pub exclude_regions: Vec<String>,
+
+    /// Only measure from regions in these countries (comma-separated, e.g. US,DE)
+    #[arg(long = "country", value_delimiter = ',')]
+    pub countries: Vec<String>,
+
+    /// Only measure from regions in these directions (comma-separated, e.g. east,west)
+    #[arg(long = "direction", value_delimiter = ',')]
+    pub directions: Vec<String>,
+}
+
+impl RegionFilter {
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+            && self.exclude_regions.is_empty()
+            && self.countries.is_empty()
+            && self.directions.is_empty()
+    }
+}
+
+/// Validates every filter value against the catalog and returns the matching regions.
+///
+/// Returns `Ok(None)` when no filter is set, meaning the host should measure all regions.
+pub fn resolve_regions(filter: &RegionFilter) -> Result<Option<Vec<PlacementRegion>>, String> {
+    if filter.is_empty() {
+        return Ok(None);
+    }
+
+    let catalog = placement_regions();
+    let hint = "Run 'borrow ms regions' to list the available values.";
+
+    for code in filter.regions.iter().chain(&filter.exclude_regions) {
+        if !catalog.iter().any(|r| &r.code == code) {
+            return Err(format!("unknown region '{code}'. {hint}"));
+        }
+    }
+    for country in &filter.countries {
+        if !catalog
+            .iter()
+            .any(|r| r.country.eq_ignore_ascii_case(country))
+        {
+            return Err(format!("unknown country '{country}'. {hint}"));
+        }
+    }
+    for direction in &filter.directions {
+        if !catalog
+            .iter()
+            .any(|r| r.direction.eq_ignore_ascii_case(direction))
+        {
+            return Err(format!("unknown direction '{direction}'. {hint}"));
+        }
+    }
+
+    let selected: Vec<PlacementRegion> = catalog
+        .into_iter()
+        .filter(|r| filter.regions.is_empty() || filter.regions.contains(&r.code))
+        .filter(|r| !filter.exclude_regions.contains(&r.code))
+        .filter(|r| {
+            filter.countries.is_empty()
+                || filter
+                    .countries
+                    .iter()
+                    .any(|c| r.country.eq_ignore_ascii_case(c))
+        })
+        .filter(|r| {
+            filter.directions.is_empty()
+                || filter
+                    .directions
+                    .iter()
+                    .any(|d| r.direction.eq_ignore_ascii_case(d))
+        })
+        .collect();
+
+    if selected.is_empty() {
+        return Err("no region matches the given filters.".to_string());
+    }
+
+    Ok(Some(selected))
+}
+
+pub fn print_regions(regions: &[PlacementRegion], format: OutputFormat) {
+    match format {
+        OutputFormat::Table => {
+            println!("{:<26} {:<34} {:<10}", "CODE", "🌎 REGION", "DIRECTION");
+            println!("{}", "-".repeat(72));
+            for r in regions {
+                let label = format!("{}  {} ({})", region_flag(&r.country), r.country, r.region);
+                println!("{:<26} {:<34} {:<10}", r.code, label, r.direction);
+            }
+        }
+        OutputFormat::Json => {
+            let output: Vec<_> = regions
+                .iter()
+                .map(|r| {
+                    serde_json::json!({
+                        "code": r.code,
+                        "country": r.country,
+                        "region": r.region,
+                        "direction": r.direction,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&output).unwrap());
+        }
+        OutputFormat::Csv => {
+            println!("code,country,region,direction");
+            for r in regions {
+                println!(
+                    "{},{},{},{}",
+                    csv_field(&r.code),
+                    csv_field(&r.country),
+                    csv_field(&r.region),
+                    csv_field(&r.direction)
+                );
+            }
+        }
+        OutputFormat::Markdown => {
+            println!("| Code | Region | Direction |");
+            println!("|------|--------|-----------|");
+            for r in regions {
+                println!(
+                    "| `{}` | {} {} ({}) | {} |",
+                    r.code,
+                    region_flag(&r.country),
+                    markdown_cell(&r.country),
+                    markdown_cell(&r.region),
+                    markdown_cell(&r.direction)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use chrono::Utc;
+
+use super::{Breakdown, MsReport, RegionRow};
+
+const CHART_WIDTH: f64 = 760.0;
+const CHART_LABEL_WIDTH: f64 = 230.0;
+const CHART_ROW_HEIGHT: f64 = 30.0;
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif; color: #1f2937; max-width: 1000px; margin: 2rem auto; padding: 0 1rem; }
+h1 { font-size: 1.4rem; word-break: break-all; }
+h2 { font-size: 1.1rem; margin-top: 2rem; }
+table { border-collapse: collapse; width: 100%; font-size: 0.9rem; }
+th, td { border-bottom: 1px solid #e5e7eb; padding: 0.4rem 0.6rem; text-align: left; }
+td.num, th.num { text-align: right; font-variant-numeric: tabular-nums; }
+dl { display: grid; grid-template-columns: max-content auto; gap: 0.3rem 1rem; font-size: 0.9rem; }
+dt { font-weight: 600; }
+dd { margin: 0; word-break: break-all; }
+code { background: #f3f4f6; padding: 0 0.2rem; border-radius: 3px; }
+.slower { color: #b91c1c; }
+.faster { color: #15803d; }
+.legend span { display: inline-block; margin-right: 1rem; font-size: 0.85rem; }
+.swatch { display: inline-block; width: 0.8rem; height: 0.8rem; margin-right: 0.3rem; vertical-align: middle; }
+footer { margin-top: 2rem; font-size: 0.8rem; color: #6b7280; }
+"#;
+
+/// A previous measurement the report is compared with.
+pub struct ReportBaseline {
+    /// Describes where the measurement comes from, e.g. the file it was read from.
+    pub label: String,
+    pub regions: Vec<RegionRow>,
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn region_label(region: &RegionRow) -> String {
+    format!("{} ({})", region.country, region.region)
+}
+
+fn delta_cell(before: f64, after: f64) -> String {
+    let delta = after - before;
+    let class = if delta > 0.0 { "slower" } else { "faster" };
+    let percent = if before != 0.0 {
+        format!(", {:+.1}%", delta / before * 100.0)
+    } else {
+        String::new()
+    };
+    format!("<td class=\"num {class}\">{delta:+.2}{percent}</td>")
+}
+
+fn write_summary(
+    html: &mut String,
+    report: &MsReport,
+    headers: &BTreeMap<String, String>,
+    baseline: Option<&ReportBaseline>,
+) {
+    html.push_str("<h2>Request</h2>\n<dl>\n");
+    let mut entry = |name: &str, value: &str| {
+        let _ = writeln!(html, "<dt>{}</dt><dd>{}</dd>", escape(name), value);
+    };
+    entry("Method", &escape(&report.method));
+    entry("URL", &format!("<code>{}</code>", escape(&report.url)));
+    entry(
+        "Request hash",
+        &format!("<code>{}</code>", report.request_hash),
+    );
+    if let Some(source) = &report.body_source {
+        entry("Body", &escape(source));
+    }
+    entry(
+        "Generated at",
+        &Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+    );
+    if let Some(baseline) = baseline {
+        entry("Compared with", &escape(&baseline.label));
+    }
+    for (name, value) in headers {
+        entry(
+            &format!("Header {name}"),
+            &format!("<code>{}</code>", escape(value)),
+        );
+    }
+    html.push_str("</dl>\n");
+}
+
+fn write_table(html: &mut String, regions: &[RegionRow], baseline: Option<&ReportBaseline>) {
+    html.push_str("<h2>Latency by region</h2>\n<table>\n<thead><tr>");
+    html.push_str("<th>#</th><th>Region</th><th>Code</th><th>Direction</th>");
+    html.push_str("<th class=\"num\">Iterations</th><th class=\"num\">p50 (ms)</th><th class=\"num\">p90 (ms)</th><th class=\"num\">p99 (ms)</th>");
+    if baseline.is_some() {
+        html.push_str("<th class=\"num\">Baseline p50 (ms)</th><th class=\"num\">p50 change</th>");
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+
+    for (i, region) in regions.iter().enumerate() {
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td><code>{}</code></td><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{:.2}</td><td class=\"num\">{:.2}</td><td class=\"num\">{:.2}</td>",
+            i + 1,
+            escape(&region_label(region)),
+            escape(&region.code),
+            escape(&region.direction),
+            region.iterations_label(),
+            region.p50,
+            region.p90,
+            region.p99
+        );
+        if let Some(baseline) = baseline {
+            match baseline.regions.iter().find(|r| r.code == region.code) {
+                Some(before) => {
+                    let _ = write!(html, "<td class=\"num\">{:.2}</td>", before.p50);
+                    html.push_str(&delta_cell(before.p50, region.p50));
+                }
+                None => html.push_str("<td class=\"num\">-</td><td class=\"num\">-</td>"),
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+}
+
+/// A horizontal bar chart, p50 over p90 over p99, with a marker for the baseline's p50.
+fn write_chart(html: &mut String, regions: &[RegionRow], baseline: Option<&ReportBaseline>) {
+    let baseline_p50 = |region: &RegionRow| {
+        baseline.and_then(|b| {
+            b.regions
+                .iter()
+                .find(|r| r.code == region.code)
+                .map(|r| r.p50)
+        })
+    };
+    let max = regions
+        .iter()
+        .flat_map(|r| [Some(r.p99), baseline_p50(r)])
+        .flatten()
+        .fold(0.0, f64::max)
+        .max(1.0);
+    let bar_width = CHART_WIDTH - CHART_LABEL_WIDTH - 70.0;
+    let scale = |value: f64| value / max * bar_width;
+    let height = regions.len() as f64 * CHART_ROW_HEIGHT + 10.0;
+
+    html.push_str("<h2>Chart</h2>\n<p class=\"legend\">");
+    html.push_str("<span><span class=\"swatch\" style=\"background:#4f46e5\"></span>p50</span>");
+    html.push_str("<span><span class=\"swatch\" style=\"background:#818cf8\"></span>p90</span>");
+    html.push_str("<span><span class=\"swatch\" style=\"background:#c7d2fe\"></span>p99</span>");
+    if baseline.is_some() {
+        html.push_str(
+            "<span><span class=\"swatch\" style=\"background:#f97316\"></span>baseline p50</span>",
+        );
+    }
+    html.push_str("</p>\n");
+    let _ = writeln!(
+        html,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{CHART_WIDTH}\" height=\"{height}\" viewBox=\"0 0 {CHART_WIDTH} {height}\" font-size=\"12\">"
+    );
+
+    for (i, region) in regions.iter().enumerate() {
+        let y = i as f64 * CHART_ROW_HEIGHT + 5.0;
+        let bar_y = y + 4.0;
+        let bar_height = CHART_ROW_HEIGHT - 10.0;
+        let _ = writeln!(
+            html,
+            "<text x=\"0\" y=\"{:.1}\" dominant-baseline=\"middle\">{}</text>",
+            y + CHART_ROW_HEIGHT / 2.0 - 1.0,
+            escape(&region_label(region))
+        );
+        for (value, color) in [
+            (region.p99, "#c7d2fe"),
+            (region.p90, "#818cf8"),
+            (region.p50, "#4f46e5"),
+        ] {
+            let _ = writeln!(
+                html,
+                "<rect x=\"{CHART_LABEL_WIDTH}\" y=\"{bar_y:.1}\" width=\"{:.1}\" height=\"{bar_height:.1}\" fill=\"{color}\"><title>{:.2} ms</title></rect>",
+                scale(value),
+                value
+            );
+        }
+        if let Some(before) = baseline_p50(region) {
+            let x = CHART_LABEL_WIDTH + scale(before);
+            let _ = writeln!(
+                html,
+                "<line x1=\"{x:.1}\" x2=\"{x:.1}\" y1=\"{:.1}\" y2=\"{:.1}\" stroke=\"#f97316\" stroke-width=\"3\"><title>baseline {before:.2} ms</title></line>",
+                bar_y - 2.0,
+                bar_y + bar_height + 2.0
+            );
+        }
+        let _ = writeln!(
+            html,
+            "<text x=\"{:.1}\" y=\"{:.1}\" dominant-baseline=\"middle\">p50 {:.0} ms</text>",
+            CHART_LABEL_WIDTH + scale(region.p99) + 6.0,
+            y + CHART_ROW_HEIGHT / 2.0 - 1.0,
+            region.p50
+        );
+    }
+    html.push_str("</svg>\n");
+}
+
+fn write_breakdown(html: &mut String, breakdown: &Breakdown) {
+    let _ = writeln!(
+        html,
+        "<h2>Phases from this machine</h2>\n<p>{} of {} requests successful.</p>",
+        breakdown.amount, breakdown.iterations
+    );
+    html.push_str("<table>\n<thead><tr><th>Phase</th><th class=\"num\">p50 (ms)</th><th class=\"num\">p90 (ms)</th><th class=\"num\">p99 (ms)</th></tr></thead>\n<tbody>\n");
+    for phase in &breakdown.phases {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td class=\"num\">{:.2}</td><td class=\"num\">{:.2}</td><td class=\"num\">{:.2}</td></tr>",
+            phase.phase, phase.p50, phase.p90, phase.p99
+        );
+    }
+    html.push_str("</tbody>\n</table>\n");
+}
+
+/// Renders the report as a standalone HTML page, without any external resource.
+pub fn render_html_report(
+    report: &MsReport,
+    headers: &BTreeMap<String, String>,
+    baseline: Option<&ReportBaseline>,
+) -> String {
+    let title = format!("API latency report for {} {}", report.method, report.url);
+    let mut html = String::new();
+    let _ = writeln!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>🏁 {}</h1>",
+        escape(&title),
+        escape(&title)
+    );
+
+    write_summary(&mut html, report, headers, baseline);
+    write_table(&mut html, &report.regions, baseline);
+    if !report.regions.is_empty() {
+        write_chart(&mut html, &report.regions, baseline);
+    }
+    if let Some(breakdown) = &report.breakdown {
+        write_breakdown(&mut html, breakdown);
+    }
+
+    html.push_str("<footer>Generated by <code>borrow ms</code>, Borrow.dev Open-Source Tools for Web Developers.</footer>\n</body>\n</html>\n");
+    html
+}
+
+/// Writes the HTML report to `path`.
+pub fn write_html_report(
+    path: &Path,
+    report: &MsReport,
+    headers: &BTreeMap<String, String>,
+    baseline: Option<&ReportBaseline>,
+) -> std::io::Result<()> {
+    std::fs::write(path, render_html_report(report, headers, baseline))
+}
@@ -0,0 +1,445 @@
+use std::{
+    collections::BTreeMap,
+    hash::{BuildHasher, RandomState},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use borrow_dev::{
+    DEFAULT_ENDPOINT, MeasureParams, MeasureRequest, MeasureResponse, MsClient, MsError,
+    get_config_path, load_config,
+};
+use clap::Args;
+
+use super::{
+    AssertionArgs, BodySource, FormFile, LOCAL_ITERATIONS, RegionFilter, RequestBody, form_body,
+    measure_locally, multipart_body, parse_body, parse_form_field, parse_form_file, progress_line,
+    resolve_regions, summarize_error,
+};
+
+/// Same bound as `MAX_ITERATIONS` in `packages/ms/src/host/constants.ts`.
+const MAX_ITERATIONS: i64 = 50;
+
+/// Arguments describing how requests are measured, shared by every measuring command.
+#[derive(Args, Debug, Clone)]
+pub struct ClientArgs {
+    /// Borrow API key (defaults to BORROW_API_KEY env var)
+    #[arg(
+        short = 'k',
+        long = "api-key",
+        env = "BORROW_API_KEY",
+        required_unless_present = "local"
+    )]
+    pub api_key: Option<String>,
+
+    /// Measure from this machine instead of the Borrow API (no API key needed)
+    #[arg(long = "local", default_value_t = false)]
+    pub local: bool,
+
+    /// Maximum time to wait for each measurement attempt (e.g. 90s, 5m)
+    #[arg(long = "timeout", value_parser = parse_duration, default_value = "3m")]
+    pub timeout: Duration,
+
+    /// Requests made from each region, more iterations give more stable percentiles (1-50)
+    #[arg(long = "iterations", value_parser = clap::value_parser!(u32).range(1..=MAX_ITERATIONS))]
+    pub iterations: Option<u32>,
+
+    /// How many times to retry after a transient failure (network errors, 429, 502-504, UPSTREAM)
+    #[arg(long = "retries", default_value_t = 2)]
+    pub retries: u32,
+
+    /// Also retry measurements that publish their results, which may publish them twice
+    #[arg(long = "retry-publish", default_value_t = false)]
+    pub retry_publish: bool,
+
+    /// Delay before the first retry, doubled after every attempt (e.g. 500ms, 2s)
+    #[arg(long = "retry-delay", value_parser = parse_duration, default_value = "1s")]
+    pub retry_delay: Duration,
+
+    /// Borrow API URL, e.g. a self-hosted ms host (defaults to BORROW_ENDPOINT, then the config file)
+    #[arg(long = "endpoint", env = "BORROW_ENDPOINT", value_parser = parse_endpoint)]
+    pub endpoint: Option<String>,
+}
+
+impl ClientArgs {
+    pub fn api_key(&self) -> &str {
+        self.api_key.as_deref().expect("API key is required")
+    }
+
+    pub fn endpoint(&self) -> String {
+        resolve_endpoint(self.endpoint.as_deref())
+    }
+
+    pub fn ms_client(&self) -> MsClient {
+        MsClient {
+            api_key: self.api_key().to_string(),
+            timeout: self.timeout,
+            endpoint: self.endpoint(),
+        }
+    }
+}
+
+/// Arguments of the commands that call the Borrow API without measuring a request.
+#[derive(Args, Debug, Clone)]
+pub struct ApiArgs {
+    /// Borrow API key (defaults to BORROW_API_KEY env var)
+    #[arg(short = 'k', long = "api-key", env = "BORROW_API_KEY")]
+    pub api_key: String,
+
+    /// Borrow API URL, e.g. a self-hosted ms host (defaults to BORROW_ENDPOINT, then the config file)
+    #[arg(long = "endpoint", env = "BORROW_ENDPOINT", value_parser = parse_endpoint)]
+    pub endpoint: Option<String>,
+}
+
+impl ApiArgs {
+    pub fn client(&self) -> MsClient {
+        let mut client = MsClient::new(&self.api_key);
+        client.endpoint = resolve_endpoint(self.endpoint.as_deref());
+        client
+    }
+}
+
+/// Arguments describing the request to measure, shared by every measuring command.
+#[derive(Args, Debug, Clone)]
+pub struct RequestArgs {
+    /// HTTP method (GET, POST, PUT, DELETE, PATCH, OPTIONS, HEAD)
+    #[arg(short = 'm', long = "method", default_value = "GET")]
+    pub method: String,
+
+    /// Headers to forward in the request (repeatable, format: "Key: Value")
+    #[arg(long = "header", num_args = 0..)]
+    pub header: Vec<String>,
+
+    /// Request body (for POST, PUT, DELETE), "@path" reads it from a file and "@-" from stdin
+    #[arg(
+        short = 'd',
+        long = "body",
+        value_parser = parse_body,
+        conflicts_with_all = ["form", "form_file"]
+    )]
+    pub body: Option<RequestBody>,
+
+    /// URL-encoded form field (repeatable, format: "key=value")
+    #[arg(long = "form", value_parser = parse_form_field)]
+    pub form: Vec<(String, String)>,
+
+    /// File to send as multipart form data, with the --form fields (repeatable, format: "field=@path")
+    #[arg(long = "form-file", value_parser = parse_form_file)]
+    pub form_file: Vec<FormFile>,
+
+    #[command(flatten)]
+    pub region_filter: RegionFilter,
+
+    #[command(flatten)]
+    pub assertions: AssertionArgs,
+
+    #[command(flatten)]
+    pub client: ClientArgs,
+}
+
+/// Parses a duration such as "90s", "5m" or "1m30s", plain numbers are seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let duration = match value.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => humantime::parse_duration(value)
+            .map_err(|e| format!("invalid duration '{value}': {e}"))?,
+    };
+    if duration.is_zero() {
+        return Err("the duration must be greater than zero".to_string());
+    }
+    Ok(duration)
+}
+
+/// Parses the base URL of a Borrow API, without its trailing slash.
+pub fn parse_endpoint(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    match reqwest::Url::parse(value) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.host_str().is_some() => {
+            Ok(value.trim_end_matches('/').to_string())
+        }
+        _ => Err(format!(
+            "invalid endpoint '{value}', expected an http(s) URL such as https://api.borrow.dev"
+        )),
+    }
+}
+
+/// Returns the Borrow API to use: the `--endpoint` flag or BORROW_ENDPOINT, then the `endpoint`
+/// of the `[ms]` section of the config file, then the default.
+pub fn resolve_endpoint(flag: Option<&str>) -> String {
+    static CONFIGURED: OnceLock<Option<String>> = OnceLock::new();
+
+    if let Some(endpoint) = flag {
+        return endpoint.to_string();
+    }
+    CONFIGURED
+        .get_or_init(|| {
+            let config = load_config().unwrap_or_else(|e| {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            });
+            config.ms.endpoint.map(|endpoint| {
+                parse_endpoint(&endpoint).unwrap_or_else(|e| {
+                    eprintln!("Error: {e} in '{}'", get_config_path().display());
+                    std::process::exit(1);
+                })
+            })
+        })
+        .clone()
+        .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+}
+
+/// Exit codes for the errors of a measurement, so that scripts can tell them apart.
+pub const INVALID_PARAMS_EXIT_CODE: i32 = 4;
+pub const UNAUTHORIZED_EXIT_CODE: i32 = 5;
+pub const UPSTREAM_EXIT_CODE: i32 = 6;
+pub const NETWORK_EXIT_CODE: i32 = 7;
+pub const TIMEOUT_EXIT_CODE: i32 = 8;
+
+pub fn error_exit_code(error: &MsError) -> i32 {
+    match error {
+        MsError::InvalidParams(_) => INVALID_PARAMS_EXIT_CODE,
+        MsError::Unauthorized(_) => UNAUTHORIZED_EXIT_CODE,
+        MsError::Http {
+            status: 401 | 403, ..
+        } => UNAUTHORIZED_EXIT_CODE,
+        MsError::Upstream(_) | MsError::UpstreamStatus { .. } => UPSTREAM_EXIT_CODE,
+        MsError::Request(_) => NETWORK_EXIT_CODE,
+        MsError::Http { status, .. } if *status >= 500 => NETWORK_EXIT_CODE,
+        MsError::Timeout(_) => TIMEOUT_EXIT_CODE,
+        _ => 1,
+    }
+}
+
+/// Returns the headers with the values of credentials replaced, for display.
+pub fn redact_headers(headers: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let lower = name.to_ascii_lowercase();
+            let sensitive = [
+                "auth", "cookie", "token", "secret", "key", "session", "password",
+            ]
+            .iter()
+            .any(|word| lower.contains(word));
+            let value = if sensitive {
+                "[redacted]"
+            } else {
+                value.as_str()
+            };
+            (name.clone(), value.to_string())
+        })
+        .collect()
+}
+
+const PUBLISH_PARTS: &[&str] = &["body", "headers", "query"];
+
+/// Checks the parts of the request given to `--publish`.
+pub fn validate_publish(parts: &[String]) -> Result<(), String> {
+    match parts.iter().find(|p| !PUBLISH_PARTS.contains(&p.as_str())) {
+        Some(part) => Err(format!(
+            "invalid publish part '{part}'. Valid options: body, headers, query."
+        )),
+        None => Ok(()),
+    }
+}
+
+/// A validated measure request, ready to be sent to the ms API.
+#[derive(Debug, Clone)]
+pub struct PreparedRequest {
+    pub url: String,
+    pub method: String,
+    pub body_source: Option<BodySource>,
+    pub params: MeasureParams,
+}
+
+impl PreparedRequest {
+    pub fn hash(&self) -> String {
+        format!(
+            "{:x}",
+            md5::compute(serde_json::to_string(&self.params.measure_request).unwrap())
+        )
+    }
+}
+
+/// Validates the request arguments and builds the `measure` payload, exiting on invalid input.
+pub fn prepare_request(url: &str, args: &RequestArgs) -> PreparedRequest {
+    let method = args.method.to_uppercase();
+    match method.as_str() {
+        "GET" | "POST" | "PUT" | "DELETE" | "PATCH" | "OPTIONS" | "HEAD" => {}
+        _ => {
+            eprintln!(
+                "Error: unsupported HTTP method '{}'. Use GET, POST, PUT, DELETE, PATCH, OPTIONS, or HEAD.",
+                method
+            );
+            std::process::exit(1);
+        }
+    }
+
+    let mut headers: BTreeMap<String, String> = BTreeMap::new();
+    for h in &args.header {
+        if let Some((key, value)) = h.split_once(':') {
+            headers.insert(key.trim().to_string(), value.trim().to_string());
+        } else {
+            eprintln!(
+                "Error: invalid header format '{}'. Expected 'Key: Value'.",
+                h
+            );
+            std::process::exit(1);
+        }
+    }
+
+    let body = if !args.form_file.is_empty() {
+        match multipart_body(&args.form, &args.form_file) {
+            Ok(body) => Some(body),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+    } else if !args.form.is_empty() {
+        Some(form_body(&args.form))
+    } else {
+        args.body.clone()
+    };
+
+    if body.is_some() && method == "GET" {
+        eprintln!("Error: --body, --form and --form-file cannot be used with GET requests.");
+        std::process::exit(1);
+    }
+
+    if let Some(content_type) = body.as_ref().and_then(|b| b.content_type.clone())
+        && !headers
+            .keys()
+            .any(|k| k.eq_ignore_ascii_case("Content-Type"))
+    {
+        headers.insert("Content-Type".to_string(), content_type);
+    }
+
+    let mut params = MeasureParams::new(MeasureRequest {
+        url: url.to_string(),
+        method: method.clone(),
+        headers,
+        body: body.as_ref().map(|b| b.content.clone()),
+    });
+    params.iterations = args.client.iterations;
+
+    if args.client.local && !args.region_filter.is_empty() {
+        eprintln!("Error: region filters cannot be used with --local.");
+        std::process::exit(1);
+    }
+
+    if args.client.local && !args.assertions.is_empty() {
+        eprintln!("Error: response assertions cannot be used with --local.");
+        std::process::exit(1);
+    }
+    params.assertions = args.assertions.to_assertions();
+
+    match resolve_regions(&args.region_filter) {
+        Ok(Some(regions)) => {
+            params.regions = Some(regions.into_iter().map(|r| r.code).collect());
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+
+    PreparedRequest {
+        url: url.to_string(),
+        method,
+        body_source: body.map(|b| b.source),
+        params,
+    }
+}
+
+/// Prepares the requests of a batch or scenario file, exiting on invalid input.
+///
+/// Every request is validated before the first one is sent, so that a typo in the last request
+/// doesn't exit after the others were already measured.
+pub fn prepare_all(requests: Vec<(&str, RequestArgs)>) -> Vec<(RequestArgs, PreparedRequest)> {
+    requests
+        .into_iter()
+        .map(|(url, args)| {
+            let request = prepare_request(url, &args);
+            (args, request)
+        })
+        .collect()
+}
+
+/// Sets the video, publish and scope options of the payload, exiting on invalid input.
+pub fn apply_publish_options(
+    request: &mut PreparedRequest,
+    args: &RequestArgs,
+    publish: &[String],
+    scope: &str,
+    no_video: bool,
+) {
+    request.params.enable_video = Some(!no_video);
+
+    if let Err(e) = validate_publish(publish) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+    request.params.publish = publish.to_vec();
+    request.params.scope = Some(scope.to_string());
+
+    if args.client.local && (!publish.is_empty() || scope == "public") {
+        eprintln!("Error: local measurements cannot be published.");
+        std::process::exit(1);
+    }
+}
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Exponential backoff with jitter, so that concurrent runs don't retry in lockstep.
+fn retry_delay(base: Duration, attempt: u32) -> Duration {
+    // A fresh RandomState is randomly seeded, which is enough for jitter.
+    let random = RandomState::new().hash_one(attempt);
+    let jitter = 0.5 + (random % 1000) as f64 / 2000.0;
+    base.saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(MAX_RETRY_DELAY)
+        .mul_f64(jitter)
+}
+
+/// Measures the request from this machine with `--local`, or through the ms API otherwise,
+/// retrying transient failures.
+pub fn execute_request(
+    args: &RequestArgs,
+    request: &PreparedRequest,
+) -> Result<MeasureResponse, MsError> {
+    let client = &args.client;
+    // A failed call may still have published the results, so it's only retried on request.
+    let publishes =
+        !request.params.publish.is_empty() || request.params.scope.as_deref() == Some("public");
+    let retries = if publishes && !client.retry_publish {
+        0
+    } else {
+        client.retries
+    };
+    let mut attempt = 0;
+    loop {
+        let result = if client.local {
+            let iterations = client.iterations.unwrap_or(LOCAL_ITERATIONS);
+            measure_locally(request, iterations, client.timeout)
+        } else {
+            client.ms_client().measure(&request.params)
+        };
+
+        match result {
+            Err(e) if e.is_retryable() && attempt < retries => {
+                attempt += 1;
+                let delay = retry_delay(client.retry_delay, attempt);
+                progress_line(&format!(
+                    "Warning: {} ({}), retrying in {:.1}s ({attempt}/{retries})",
+                    summarize_error(&e.to_string()),
+                    request.url,
+                    delay.as_secs_f64(),
+                ));
+                std::thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use borrow_dev::MeasureResponse;
+use chrono::{DateTime, Utc};
+
+use super::{RegionRow, parse_regions, region_from_json};
+
+/// The results of a successful measurement.
+pub struct MeasurementResults {
+    pub regions: Vec<RegionRow>,
+    pub status: Option<u16>,
+    pub share_url: Option<String>,
+    pub video_url: Option<String>,
+}
+
+impl MeasurementResults {
+    pub fn from_response(response: &MeasureResponse) -> Self {
+        Self {
+            regions: parse_regions(response),
+            status: Some(response.status),
+            share_url: response.url.clone(),
+            video_url: response.video_url.clone(),
+        }
+    }
+}
+
+/// A measurement read back from a file, with whatever request metadata the file contains.
+pub struct SavedMeasurement {
+    pub method: Option<String>,
+    pub url: Option<String>,
+    pub request_hash: Option<String>,
+    pub body_source: Option<String>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub outcome: Result<MeasurementResults, String>,
+}
+
+fn string_field(value: &serde_json::Value, field: &str) -> Option<String> {
+    value[field].as_str().map(str::to_string)
+}
+
+/// Reads a `-o json` report, a history run, an ms API response or a line written by `borrow ms watch --ndjson`.
+pub fn parse_saved_measurement(value: serde_json::Value) -> Result<SavedMeasurement, String> {
+    let mut saved = SavedMeasurement {
+        method: string_field(&value, "method"),
+        url: string_field(&value, "url"),
+        request_hash: string_field(&value, "requestHash"),
+        body_source: string_field(&value, "bodySource"),
+        timestamp: value["timestamp"]
+            .as_str()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Utc)),
+        outcome: Err(String::new()),
+    };
+
+    saved.outcome = if value.get("error").is_some() {
+        Err(value["error"]["message"]
+            .as_str()
+            .or(value["message"].as_str())
+            .unwrap_or("unknown error")
+            .to_string())
+    } else if let Some(regions) = value["regions"].as_array() {
+        let regions = regions
+            .iter()
+            .map(region_from_json)
+            .collect::<Option<Vec<_>>>()
+            .ok_or("invalid region entry")?;
+        Ok(MeasurementResults {
+            regions,
+            status: None,
+            share_url: string_field(&value, "shareUrl"),
+            video_url: string_field(&value, "videoUrl"),
+        })
+    } else {
+        let response = if value.get("response").is_some() {
+            value["response"].clone()
+        } else {
+            // A raw API response has its own `url` field, the share URL.
+            saved.url = None;
+            value
+        };
+        let response: MeasureResponse = serde_json::from_value(response)
+            .map_err(|e| format!("not a saved measurement: {e}"))?;
+        Ok(MeasurementResults::from_response(&response))
+    };
+
+    Ok(saved)
+}
+
+/// Reads the measurements saved in a file, either a single JSON document or one document per line.
+pub fn load_saved_measurements(path: &Path) -> Result<Vec<SavedMeasurement>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read '{}': {e}", path.display()))?;
+
+    if let Ok(value) = serde_json::from_str(&content) {
+        return parse_saved_measurement(value)
+            .map(|saved| vec![saved])
+            .map_err(|e| format!("'{}': {e}", path.display()));
+    }
+
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| e.to_string())
+                .and_then(parse_saved_measurement)
+                .map_err(|e| format!("'{}' line {}: {e}", path.display(), i + 1))
+        })
+        .collect()
+}
+
+/// Reads the most recent successful measurement of a file.
+pub fn load_latest_measurement(
+    path: &Path,
+) -> Result<(SavedMeasurement, MeasurementResults), String> {
+    load_saved_measurements(path)?
+        .into_iter()
+        .rev()
+        .find_map(|mut saved| {
+            let results = std::mem::replace(&mut saved.outcome, Err(String::new())).ok()?;
+            Some((saved, results))
+        })
+        .ok_or_else(|| format!("'{}' contains no successful measurement.", path.display()))
+}
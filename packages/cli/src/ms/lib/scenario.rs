@@ -0,0 +1,702 @@
+use std::{collections::BTreeMap, path::Path};
+
+use borrow_dev::{HeaderAssertion, JsonPathAssertion, MsError};
+
+use serde::{Deserialize, Deserializer};
+
+use super::{
+    AssertionArgs, ClientArgs, HistoryRecord, OutputFormat, RegionFilter, RegionRow, RequestArgs,
+    RequestBody, Threshold, Thresholds, Violation, apply_publish_options, csv_field,
+    execute_request, markdown_cell, parse_header_assertion, parse_json_path_assertion,
+    parse_regions, parse_threshold, prepare_all, progress_line, region_to_json, resolve_regions,
+    save_history_record, validate_publish, with_progress,
+};
+
+/// Scenario file used by `borrow ms run` when none is given.
+pub const DEFAULT_SCENARIO_FILE: &str = "borrow-ms.toml";
+
+/// A named request of a scenario file.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Scenario {
+    pub name: String,
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: Option<String>,
+    #[serde(default)]
+    pub regions: Vec<String>,
+    #[serde(default)]
+    pub exclude_regions: Vec<String>,
+    #[serde(default)]
+    pub country: Vec<String>,
+    #[serde(default)]
+    pub direction: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_thresholds")]
+    pub max_p50: Vec<Threshold>,
+    #[serde(default, deserialize_with = "deserialize_thresholds")]
+    pub max_p90: Vec<Threshold>,
+    #[serde(default, deserialize_with = "deserialize_thresholds")]
+    pub max_p99: Vec<Threshold>,
+    #[serde(default)]
+    pub publish: Vec<String>,
+    #[serde(default = "default_scope")]
+    pub scope: String,
+    #[serde(default)]
+    pub no_video: bool,
+    /// Response assertions, like the `--expect-*` flags.
+    #[serde(default)]
+    pub expect: ScenarioExpect,
+}
+
+/// The `[scenario.expect]` table of a scenario.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ScenarioExpect {
+    #[serde(default)]
+    pub status: Vec<u16>,
+    #[serde(default, deserialize_with = "deserialize_header_assertions")]
+    pub headers: Vec<HeaderAssertion>,
+    #[serde(default)]
+    pub body_contains: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_json_path_assertions")]
+    pub json_path: Vec<JsonPathAssertion>,
+}
+
+impl ScenarioExpect {
+    pub fn assertion_args(&self) -> AssertionArgs {
+        AssertionArgs {
+            status: self.status.clone(),
+            headers: self.headers.clone(),
+            body_contains: self.body_contains.clone(),
+            json_path: self.json_path.clone(),
+        }
+    }
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn default_scope() -> String {
+    "private".to_string()
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ThresholdValue {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ThresholdValues {
+    One(ThresholdValue),
+    Many(Vec<ThresholdValue>),
+}
+
+/// Accepts the same values as `--max-p50` and friends: `800`, `"AU=1200"` or a list of them.
+fn deserialize_thresholds<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Threshold>, D::Error> {
+    let values = match ThresholdValues::deserialize(deserializer)? {
+        ThresholdValues::One(value) => vec![value],
+        ThresholdValues::Many(values) => values,
+    };
+    values
+        .into_iter()
+        .map(|value| match value {
+            ThresholdValue::Number(max) => parse_threshold(&max.to_string()),
+            ThresholdValue::Text(text) => parse_threshold(&text),
+        })
+        .collect::<Result<_, _>>()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Accepts the same values as `--expect-header`, e.g. `["Content-Type: application/json"]`.
+fn deserialize_header_assertions<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<HeaderAssertion>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|value| parse_header_assertion(value))
+        .collect::<Result<_, _>>()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Accepts the same values as `--expect-json-path`, e.g. `["$.ok == true"]`.
+fn deserialize_json_path_assertions<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<JsonPathAssertion>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|value| parse_json_path_assertion(value))
+        .collect::<Result<_, _>>()
+        .map_err(serde::de::Error::custom)
+}
+
+impl Scenario {
+    /// Headers in the "Key: Value" format used by `--header`.
+    pub fn header_args(&self) -> Vec<String> {
+        self.headers
+            .iter()
+            .map(|(key, value)| format!("{key}: {value}"))
+            .collect()
+    }
+
+    pub fn region_filter(&self) -> RegionFilter {
+        RegionFilter {
+            regions: self.regions.clone(),
+            exclude_regions: self.exclude_regions.clone(),
+            countries: self.country.clone(),
+            directions: self.direction.clone(),
+        }
+    }
+
+    pub fn thresholds(&self) -> Thresholds {
+        Thresholds {
+            max_p50: self.max_p50.clone(),
+            max_p90: self.max_p90.clone(),
+            max_p99: self.max_p99.clone(),
+        }
+    }
+
+    fn validate(&mut self) -> Result<(), String> {
+        self.method = self.method.to_uppercase();
+        match self.method.as_str() {
+            "GET" | "POST" | "PUT" | "DELETE" | "PATCH" | "OPTIONS" | "HEAD" => {}
+            _ => {
+                return Err(format!(
+                    "unsupported HTTP method '{}'. Use GET, POST, PUT, DELETE, PATCH, OPTIONS, or HEAD.",
+                    self.method
+                ));
+            }
+        }
+        if self.method == "GET" && self.body.is_some() {
+            return Err("a body cannot be used with GET requests.".to_string());
+        }
+        if self.scope != "private" && self.scope != "public" {
+            return Err(format!(
+                "invalid scope '{}'. Valid options: private, public.",
+                self.scope
+            ));
+        }
+        if let Some(status) = self
+            .expect
+            .status
+            .iter()
+            .find(|s| !(100..=599).contains(*s))
+        {
+            return Err(format!(
+                "invalid expected status {status}, expected a value between 100 and 599."
+            ));
+        }
+        validate_publish(&self.publish)?;
+        resolve_regions(&self.region_filter())?;
+        Ok(())
+    }
+}
+
+/// Replaces every `${NAME}` in the value with the NAME environment variable, `$${` is a literal `${`.
+fn interpolate_env(value: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if let Some(after) = tail.strip_prefix("$${") {
+            result.push_str("${");
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix("${") {
+            let Some(end) = after.find('}') else {
+                return Err(format!("unterminated '${{' in '{value}'"));
+            };
+            let name = &after[..end];
+            let variable = std::env::var(name)
+                .map_err(|_| format!("environment variable '{name}' is not set"))?;
+            result.push_str(&variable);
+            rest = &after[end + 1..];
+        } else {
+            result.push('$');
+            rest = &tail[1..];
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn interpolate_value(value: &mut toml::Value) -> Result<(), String> {
+    match value {
+        toml::Value::String(s) => *s = interpolate_env(s)?,
+        toml::Value::Array(values) => {
+            for value in values {
+                interpolate_value(value)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, value) in table.iter_mut() {
+                interpolate_value(value)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Reads and validates the scenarios of a scenario file, resolving `${NAME}` environment variables.
+pub fn load_scenarios(path: &Path) -> Result<Vec<Scenario>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read '{}': {e}", path.display()))?;
+    let mut document: toml::Table = content
+        .parse()
+        .map_err(|e| format!("failed to parse '{}': {e}", path.display()))?;
+
+    if let Some(key) = document.keys().find(|key| *key != "scenario") {
+        return Err(format!(
+            "unknown key '{key}' in '{}', expected [[scenario]] entries.",
+            path.display()
+        ));
+    }
+    let Some(toml::Value::Array(entries)) = document.remove("scenario") else {
+        return Err(format!("'{}' has no [[scenario]] entries.", path.display()));
+    };
+
+    let mut scenarios: Vec<Scenario> = Vec::with_capacity(entries.len());
+    for (i, mut entry) in entries.into_iter().enumerate() {
+        let label = entry
+            .get("name")
+            .and_then(|name| name.as_str())
+            .map_or(format!("#{}", i + 1), |name| format!("'{name}'"));
+
+        let mut scenario: Scenario = interpolate_value(&mut entry)
+            .and_then(|_| entry.try_into().map_err(|e: toml::de::Error| e.to_string()))
+            .and_then(|mut scenario: Scenario| scenario.validate().map(|_| scenario))
+            .map_err(|e| format!("scenario {label}: {}", e.trim_end()))?;
+
+        scenario.name = scenario.name.trim().to_string();
+        if scenario.name.is_empty() {
+            return Err(format!("scenario {label}: the name cannot be empty."));
+        }
+        if scenarios.iter().any(|s| s.name == scenario.name) {
+            return Err(format!("scenario {label} is defined more than once."));
+        }
+        scenarios.push(scenario);
+    }
+
+    if scenarios.is_empty() {
+        return Err(format!("'{}' has no [[scenario]] entries.", path.display()));
+    }
+    Ok(scenarios)
+}
+
+/// A successful measurement of a scenario, checked against its thresholds.
+pub struct ScenarioRun {
+    pub regions: Vec<RegionRow>,
+    pub violations: Vec<Violation>,
+    pub share_url: Option<String>,
+    pub video_url: Option<String>,
+}
+
+impl ScenarioRun {
+    /// Responses that failed the assertions, in every region.
+    pub fn assertion_failures(&self) -> u64 {
+        self.regions
+            .iter()
+            .filter_map(|r| r.assertion_failures)
+            .sum()
+    }
+}
+
+pub struct ScenarioResult {
+    pub scenario: Scenario,
+    pub request_hash: String,
+    pub outcome: Result<ScenarioRun, MsError>,
+}
+
+impl ScenarioResult {
+    pub fn status(&self) -> &'static str {
+        match &self.outcome {
+            Ok(run) if run.violations.is_empty() && run.assertion_failures() == 0 => "passed",
+            Ok(_) => "failed",
+            Err(_) => "error",
+        }
+    }
+
+    fn worst_p99(&self) -> Option<&RegionRow> {
+        self.outcome
+            .as_ref()
+            .ok()?
+            .regions
+            .iter()
+            .max_by(|a, b| a.p99.total_cmp(&b.p99))
+    }
+
+    fn best_p50(&self) -> Option<&RegionRow> {
+        self.outcome
+            .as_ref()
+            .ok()?
+            .regions
+            .iter()
+            .min_by(|a, b| a.p50.total_cmp(&b.p50))
+    }
+}
+
+/// Measures every scenario one after the other, printing progress to stderr.
+pub fn run_scenarios(
+    scenarios: Vec<Scenario>,
+    client: &ClientArgs,
+    save_history: bool,
+) -> Vec<ScenarioResult> {
+    let prepared = prepare_all(
+        scenarios
+            .iter()
+            .map(|scenario| {
+                let args = RequestArgs {
+                    method: scenario.method.clone(),
+                    header: scenario.header_args(),
+                    body: scenario.body.clone().map(RequestBody::inline),
+                    form: Vec::new(),
+                    form_file: Vec::new(),
+                    region_filter: scenario.region_filter(),
+                    assertions: scenario.expect.assertion_args(),
+                    client: client.clone(),
+                };
+                (scenario.url.as_str(), args)
+            })
+            .collect(),
+    );
+    let requests: Vec<_> = scenarios
+        .into_iter()
+        .zip(prepared)
+        .map(|(scenario, (args, mut request))| {
+            apply_publish_options(
+                &mut request,
+                &args,
+                &scenario.publish,
+                &scenario.scope,
+                scenario.no_video,
+            );
+            (scenario, args, request)
+        })
+        .collect();
+
+    let total = requests.len();
+    let mut results = Vec::with_capacity(total);
+    for (i, (scenario, args, request)) in requests.into_iter().enumerate() {
+        progress_line(&format!("[{}/{}] {}", i + 1, total, scenario.name));
+        let request_hash = request.hash();
+
+        let outcome = with_progress(&format!("Measuring {}", scenario.name), || {
+            execute_request(&args, &request)
+        })
+        .map(|response| {
+            let regions = parse_regions(&response);
+            let run = ScenarioRun {
+                violations: scenario.thresholds().evaluate(&regions),
+                regions,
+                share_url: response.url.clone().filter(|_| scenario.scope == "public"),
+                video_url: response.video_url.clone(),
+            };
+            if save_history {
+                let mut record =
+                    HistoryRecord::new(&request.method, &request.url, &request_hash, response);
+                if let Err(e) = save_history_record(&mut record) {
+                    eprintln!("Warning: failed to save run to history: {e}");
+                }
+            }
+            run
+        });
+
+        results.push(ScenarioResult {
+            scenario,
+            request_hash,
+            outcome,
+        });
+    }
+    results
+}
+
+pub fn print_scenario_report(results: &[ScenarioResult], format: OutputFormat) {
+    match format {
+        OutputFormat::Table => print_table(results),
+        OutputFormat::Json => print_json(results),
+        OutputFormat::Csv => print_csv(results),
+        OutputFormat::Markdown => print_markdown(results),
+    }
+}
+
+fn status_label(result: &ScenarioResult) -> &'static str {
+    match result.status() {
+        "passed" => "✅ passed",
+        "failed" => "❌ failed",
+        _ => "⚠️  error",
+    }
+}
+
+fn count_status(results: &[ScenarioResult], status: &str) -> usize {
+    results.iter().filter(|r| r.status() == status).count()
+}
+
+fn print_table(results: &[ScenarioResult]) {
+    println!("=================================================================================");
+    println!("🏁 API latency stats for {} scenario(s)", results.len());
+    println!();
+    println!(
+        "{:<4} {:<24} {:<10} {:>7} {:>10} {:>10}  WORST REGION",
+        "#", "SCENARIO", "RESULT", "REGIONS", "BEST p50", "WORST p99"
+    );
+    println!("{}", "-".repeat(104));
+
+    for (i, result) in results.iter().enumerate() {
+        let best = result.best_p50();
+        let worst = result.worst_p99();
+        println!(
+            "{:<4} {:<24} {:<10} {:>7} {:>10} {:>10}  {}",
+            i + 1,
+            result.scenario.name,
+            status_label(result),
+            result
+                .outcome
+                .as_ref()
+                .map_or("-".to_string(), |run| run.regions.len().to_string()),
+            best.map_or("-".to_string(), |r| format!("{:.2}", r.p50)),
+            worst.map_or("-".to_string(), |r| format!("{:.2}", r.p99)),
+            worst.map_or("-", |r| r.code.as_str()),
+        );
+
+        match &result.outcome {
+            Ok(run) => {
+                for r in run
+                    .regions
+                    .iter()
+                    .filter(|r| r.assertion_failures > Some(0))
+                {
+                    println!(
+                        "     {} ({}, {}): {} responses failed the assertions, first: {}",
+                        r.code,
+                        r.country,
+                        r.region,
+                        r.assertion_failures_label(),
+                        r.first_assertion_failure.as_deref().unwrap_or("-")
+                    );
+                }
+                for v in &run.violations {
+                    println!(
+                        "     {} ({}, {}): {} {:.2} ms > {:.2} ms",
+                        v.region.code,
+                        v.region.country,
+                        v.region.region,
+                        v.percentile,
+                        v.value,
+                        v.max
+                    );
+                }
+                if let Some(url) = &run.share_url {
+                    println!("     🏎️  Share the results: {url}");
+                }
+                if let Some(video_url) = &run.video_url {
+                    println!("     🎬 Share the video: {video_url}");
+                }
+            }
+            Err(e) => println!("     Error: {e}"),
+        }
+    }
+
+    println!();
+    println!(
+        "{} passed, {} failed, {} error(s)",
+        count_status(results, "passed"),
+        count_status(results, "failed"),
+        count_status(results, "error")
+    );
+    println!("=================================================================================");
+    println!("❤️  By Borrow.dev \u{21C0} Open-Source Tools for Web Developers");
+    println!();
+}
+
+fn print_json(results: &[ScenarioResult]) {
+    let scenarios: Vec<_> = results
+        .iter()
+        .map(|result| {
+            let run = result.outcome.as_ref().ok();
+            serde_json::json!({
+                "name": result.scenario.name,
+                "method": result.scenario.method,
+                "url": result.scenario.url,
+                "requestHash": result.request_hash,
+                "status": result.status(),
+                "regions": run.map(|run| run.regions.iter().map(region_to_json).collect::<Vec<_>>()),
+                "assertionFailures": run.map(ScenarioRun::assertion_failures),
+                "violations": run.map(|run| {
+                    run.violations
+                        .iter()
+                        .map(|v| {
+                            serde_json::json!({
+                                "region": v.region.code,
+                                "percentile": v.percentile,
+                                "value": v.value,
+                                "max": v.max,
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                }),
+                "shareUrl": run.and_then(|run| run.share_url.clone()),
+                "videoUrl": run.and_then(|run| run.video_url.clone()),
+                "error": result.outcome.as_ref().err().map(|e| format!("Error: {e}")),
+            })
+        })
+        .collect();
+
+    let output = serde_json::json!({
+        "passed": count_status(results, "passed"),
+        "failed": count_status(results, "failed"),
+        "errors": count_status(results, "error"),
+        "scenarios": scenarios,
+    });
+    println!("{}", serde_json::to_string_pretty(&output).unwrap());
+}
+
+fn print_csv(results: &[ScenarioResult]) {
+    println!(
+        "name,method,url,status,regions,best_p50,worst_p99,worst_region,violations,assertionFailures,error"
+    );
+    for result in results {
+        let best = result.best_p50();
+        let worst = result.worst_p99();
+        println!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            csv_field(&result.scenario.name),
+            csv_field(&result.scenario.method),
+            csv_field(&result.scenario.url),
+            result.status(),
+            result.outcome.as_ref().map_or(0, |run| run.regions.len()),
+            best.map_or(String::new(), |r| format!("{:.2}", r.p50)),
+            worst.map_or(String::new(), |r| format!("{:.2}", r.p99)),
+            worst.map_or(String::new(), |r| csv_field(&r.code)),
+            result
+                .outcome
+                .as_ref()
+                .map_or(0, |run| run.violations.len()),
+            result
+                .outcome
+                .as_ref()
+                .map_or(0, |run| run.assertion_failures()),
+            result
+                .outcome
+                .as_ref()
+                .err()
+                .map_or(String::new(), |e| csv_field(&format!("Error: {e}")))
+        );
+    }
+}
+
+fn print_markdown(results: &[ScenarioResult]) {
+    println!("| # | Scenario | Result | Regions | Best p50 (ms) | Worst p99 (ms) | Details |");
+    println!("|--:|----------|--------|--------:|--------------:|---------------:|---------|");
+    for (i, result) in results.iter().enumerate() {
+        let best = result.best_p50();
+        let worst = result.worst_p99();
+        let details = match &result.outcome {
+            Ok(run) => run
+                .regions
+                .iter()
+                .filter(|r| r.assertion_failures > Some(0))
+                .map(|r| {
+                    format!(
+                        "`{}` {} responses failed the assertions",
+                        r.code,
+                        r.assertion_failures_label()
+                    )
+                })
+                .chain(run.violations.iter().map(|v| {
+                    format!(
+                        "`{}` {} {:.2} ms > {:.2} ms",
+                        v.region.code, v.percentile, v.value, v.max
+                    )
+                }))
+                .collect::<Vec<_>>()
+                .join("<br>"),
+            Err(e) => markdown_cell(&format!("Error: {e}")),
+        };
+        println!(
+            "| {} | {} | {} | {} | {} | {} | {} |",
+            i + 1,
+            markdown_cell(&result.scenario.name),
+            status_label(result),
+            result
+                .outcome
+                .as_ref()
+                .map_or("-".to_string(), |run| run.regions.len().to_string()),
+            best.map_or("-".to_string(), |r| format!("{:.2}", r.p50)),
+            worst.map_or("-".to_string(), |r| format!("{:.2}", r.p99)),
+            details
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolates_environment_variables() {
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(
+            interpolate_env("a ${PATH} b").unwrap(),
+            format!("a {path} b")
+        );
+        assert_eq!(interpolate_env("costs $5, $").unwrap(), "costs $5, $");
+    }
+
+    #[test]
+    fn double_dollar_is_a_literal() {
+        assert_eq!(
+            interpolate_env("echo $${PATH} $$5").unwrap(),
+            "echo ${PATH} $$5"
+        );
+    }
+
+    #[test]
+    fn rejects_missing_and_unterminated_variables() {
+        assert!(interpolate_env("${BORROW_MS_TEST_UNSET_VARIABLE}").is_err());
+        assert!(interpolate_env("${PATH").is_err());
+    }
+
+    fn parse_scenario(extra: &str) -> Result<Scenario, String> {
+        toml::from_str::<Scenario>(&format!(
+            "name = \"api\"\nurl = \"https://example.com\"\n{extra}"
+        ))
+        .map_err(|e| e.to_string())
+        .and_then(|mut scenario| scenario.validate().map(|_| scenario))
+    }
+
+    #[test]
+    fn reads_the_expect_table() {
+        let scenario = parse_scenario(
+            "[expect]\nstatus = [200]\nheaders = [\"Content-Type: json\"]\n\
+             body-contains = [\"ok\"]\njson-path = [\"$.ok == true\"]",
+        )
+        .unwrap();
+        let assertions = scenario.expect.assertion_args().to_assertions().unwrap();
+        assert_eq!(assertions.status, vec![200]);
+        assert_eq!(assertions.headers[0].name, "Content-Type");
+        assert_eq!(assertions.headers[0].value.as_deref(), Some("json"));
+        assert_eq!(assertions.body_contains, vec!["ok"]);
+        assert_eq!(assertions.json_path[0].path, "$.ok");
+        assert_eq!(assertions.json_path[0].value, Some(serde_json::json!(true)));
+
+        let scenario = parse_scenario("").unwrap();
+        assert!(scenario.expect.assertion_args().to_assertions().is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_expectations() {
+        assert!(parse_scenario("[expect]\nstatus = [42]").is_err());
+        assert!(parse_scenario("[expect]\nheaders = [\"Bad Name\"]").is_err());
+        assert!(parse_scenario("[expect]\njson-path = [\"$.ok ==\"]").is_err());
+        assert!(parse_scenario("[expect]\ncontains = [\"ok\"]").is_err());
+    }
+}
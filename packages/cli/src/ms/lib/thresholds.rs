@@ -0,0 +1,253 @@
+use clap::Args;
+
+use super::{RegionRow, placement_regions};
+
+/// Exit code used when at least one region exceeds a latency threshold.
+pub const THRESHOLD_EXIT_CODE: i32 = 3;
+
+#[derive(Debug, Clone)]
+pub struct Threshold {
+    /// Region code or country code the limit applies to, `None` for every region.
+    pub scope: Option<String>,
+    pub max: f64,
+}
+
+pub fn parse_threshold(value: &str) -> Result<Threshold, String> {
+    let (scope, max) = match value.split_once('=') {
+        Some((scope, max)) => (Some(scope.trim().to_string()), max),
+        None => (None, value),
+    };
+
+    let max: f64 = max
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid latency '{}', expected milliseconds", max.trim()))?;
+    if !max.is_finite() || max < 0.0 {
+        return Err(format!(
+            "invalid latency '{max}', expected a positive number"
+        ));
+    }
+
+    if let Some(scope) = &scope {
+        let catalog = placement_regions();
+        let known = catalog
+            .iter()
+            .any(|r| &r.code == scope || r.country.eq_ignore_ascii_case(scope));
+        if !known {
+            return Err(format!(
+                "unknown region or country '{scope}'. Run 'borrow ms regions' to list the available values."
+            ));
+        }
+    }
+
+    Ok(Threshold { scope, max })
+}
+
+#[derive(Args, Debug, Default, Clone)]
+pub struct Thresholds {
+    /// Fail when p50 exceeds this many milliseconds (repeatable, "200" or "<region|country>=200")
+    #[arg(long = "max-p50", value_parser = parse_threshold)]
+    pub max_p50: Vec<Threshold>,
+
+    /// Fail when p90 exceeds this many milliseconds (repeatable, "400" or "<region|country>=400")
+    #[arg(long = "max-p90", value_parser = parse_threshold)]
+    pub max_p90: Vec<Threshold>,
+
+    /// Fail when p99 exceeds this many milliseconds (repeatable, "800" or "<region|country>=800")
+    #[arg(long = "max-p99", value_parser = parse_threshold)]
+    pub max_p99: Vec<Threshold>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Violation {
+    pub region: RegionRow,
+    pub percentile: &'static str,
+    pub value: f64,
+    pub max: f64,
+}
+
+/// Picks the most specific limit for a region: region code, then country, then global.
+/// The last occurrence wins when the same scope is given more than once.
+fn limit_for(thresholds: &[Threshold], region: &RegionRow) -> Option<f64> {
+    let by_region = thresholds
+        .iter()
+        .rev()
+        .find(|t| t.scope.as_deref() == Some(region.code.as_str()));
+    let by_country = || {
+        thresholds.iter().rev().find(|t| {
+            t.scope
+                .as_deref()
+                .is_some_and(|s| s.eq_ignore_ascii_case(&region.country))
+        })
+    };
+    let global = || thresholds.iter().rev().find(|t| t.scope.is_none());
+
+    by_region.or_else(by_country).or_else(global).map(|t| t.max)
+}
+
+impl Thresholds {
+    pub fn is_empty(&self) -> bool {
+        self.max_p50.is_empty() && self.max_p90.is_empty() && self.max_p99.is_empty()
+    }
+
+    pub fn evaluate(&self, regions: &[RegionRow]) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for region in regions {
+            let checks = [
+                ("p50", region.p50, &self.max_p50),
+                ("p90", region.p90, &self.max_p90),
+                ("p99", region.p99, &self.max_p99),
+            ];
+            for (percentile, value, thresholds) in checks {
+                if let Some(max) = limit_for(thresholds, region)
+                    && value > max
+                {
+                    violations.push(Violation {
+                        region: region.clone(),
+                        percentile,
+                        value,
+                        max,
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// Prints the outcome of the threshold checks to stderr and exits if any region failed.
+pub fn enforce_thresholds(thresholds: &Thresholds, regions: &[RegionRow]) {
+    if thresholds.is_empty() {
+        return;
+    }
+
+    let violations = thresholds.evaluate(regions);
+    if violations.is_empty() {
+        eprintln!("✅ All latency thresholds passed.");
+        return;
+    }
+
+    let mut failed_regions: Vec<&str> = violations.iter().map(|v| v.region.code.as_str()).collect();
+    failed_regions.dedup();
+
+    eprintln!(
+        "❌ Latency thresholds exceeded in {} region(s):",
+        failed_regions.len()
+    );
+    for v in &violations {
+        eprintln!(
+            "  {} ({}, {}): {} {:.2} ms > {:.2} ms",
+            v.region.code, v.region.country, v.region.region, v.percentile, v.value, v.max
+        );
+    }
+    std::process::exit(THRESHOLD_EXIT_CODE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(code: &str, country: &str, p50: f64, p90: f64, p99: f64) -> RegionRow {
+        RegionRow {
+            code: code.to_string(),
+            country: country.to_string(),
+            region: "Test".to_string(),
+            direction: "east".to_string(),
+            amount: 10,
+            iterations: Some(10),
+            assertion_failures: None,
+            first_assertion_failure: None,
+            p50,
+            p90,
+            p99,
+        }
+    }
+
+    #[test]
+    fn parses_global_and_scoped_thresholds() {
+        let global = parse_threshold(" 250.5 ").unwrap();
+        assert_eq!(global.scope, None);
+        assert_eq!(global.max, 250.5);
+
+        let by_region = parse_threshold("aws:us-east-1=800").unwrap();
+        assert_eq!(by_region.scope.as_deref(), Some("aws:us-east-1"));
+        assert_eq!(by_region.max, 800.0);
+
+        let by_country = parse_threshold("au = 1200").unwrap();
+        assert_eq!(by_country.scope.as_deref(), Some("au"));
+        assert_eq!(by_country.max, 1200.0);
+    }
+
+    #[test]
+    fn rejects_invalid_thresholds() {
+        assert!(parse_threshold("fast").is_err());
+        assert!(parse_threshold("-1").is_err());
+        assert!(parse_threshold("inf").is_err());
+        assert!(parse_threshold("aws:us-east-1=").is_err());
+        assert!(parse_threshold("mars:north-1=100").is_err());
+    }
+
+    #[test]
+    fn most_specific_limit_wins() {
+        let thresholds = vec![
+            parse_threshold("aws:us-east-1=300").unwrap(),
+            parse_threshold("100").unwrap(),
+            parse_threshold("US=200").unwrap(),
+        ];
+        assert_eq!(
+            limit_for(&thresholds, &region("aws:us-east-1", "US", 0.0, 0.0, 0.0)),
+            Some(300.0)
+        );
+        assert_eq!(
+            limit_for(&thresholds, &region("aws:us-west-2", "US", 0.0, 0.0, 0.0)),
+            Some(200.0)
+        );
+        assert_eq!(
+            limit_for(&thresholds, &region("aws:eu-west-1", "IE", 0.0, 0.0, 0.0)),
+            Some(100.0)
+        );
+        assert_eq!(
+            limit_for(&[], &region("aws:eu-west-1", "IE", 0.0, 0.0, 0.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn last_occurrence_of_a_scope_wins() {
+        let thresholds = vec![
+            parse_threshold("100").unwrap(),
+            parse_threshold("150").unwrap(),
+        ];
+        assert_eq!(
+            limit_for(&thresholds, &region("aws:eu-west-1", "IE", 0.0, 0.0, 0.0)),
+            Some(150.0)
+        );
+    }
+
+    #[test]
+    fn reports_only_values_above_the_limit() {
+        let thresholds = Thresholds {
+            max_p50: vec![parse_threshold("100").unwrap()],
+            max_p90: Vec::new(),
+            max_p99: vec![parse_threshold("400").unwrap()],
+        };
+        let regions = [
+            region("aws:us-east-1", "US", 100.0, 900.0, 400.0),
+            region("aws:eu-west-1", "IE", 120.0, 150.0, 500.0),
+        ];
+
+        let violations = thresholds.evaluate(&regions);
+        let found: Vec<_> = violations
+            .iter()
+            .map(|v| (v.region.code.as_str(), v.percentile, v.value, v.max))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("aws:eu-west-1", "p50", 120.0, 100.0),
+                ("aws:eu-west-1", "p99", 500.0, 400.0),
+            ]
+        );
+        assert!(Thresholds::default().evaluate(&regions).is_empty());
+    }
+}
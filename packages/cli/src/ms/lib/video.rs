@@ -0,0 +1,164 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use borrow_dev::{MsClient, MsError};
+
+/// Time between two checks of a video that is still being rendered.
+const VIDEO_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Downloads the video of a measurement that was just published, waiting up to `timeout` for it.
+///
+/// The video is only uploaded once it is rendered, until then its URL answers with a 404.
+pub fn wait_for_video(client: &MsClient, url: &str, timeout: Duration) -> Result<Vec<u8>, MsError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match client.fetch_video(url) {
+            Ok(video) => return Ok(video),
+            Err(MsError::Http { status: 404, .. }) => {}
+            Err(e) => return Err(e),
+        }
+        if Instant::now() + VIDEO_POLL_INTERVAL > deadline {
+            return Err(MsError::Timeout(timeout));
+        }
+        std::thread::sleep(VIDEO_POLL_INTERVAL);
+    }
+}
+
+/// The video `borrow ms video` downloads.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VideoTarget {
+    /// The ID of a published measurement, the end of its share URL.
+    Id(String),
+    /// The `videoUrl` of a published measurement.
+    Url(String),
+}
+
+fn is_plain_name(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Parses a measurement ID, or a video URL when it starts with `http://` or `https://`.
+///
+/// IDs are plain names, so that they can't escape their path in the API URL or the file name.
+pub fn parse_video_target(value: &str) -> Result<VideoTarget, String> {
+    if value.starts_with("http://") || value.starts_with("https://") {
+        return match reqwest::Url::parse(value) {
+            Ok(_) => Ok(VideoTarget::Url(value.to_string())),
+            Err(e) => Err(format!("invalid video URL '{value}': {e}")),
+        };
+    }
+    if is_plain_name(value) {
+        Ok(VideoTarget::Id(value.to_string()))
+    } else {
+        Err(format!(
+            "invalid measurement ID '{value}', expected the end of its share URL or its video URL"
+        ))
+    }
+}
+
+impl VideoTarget {
+    /// Where the video is downloaded from.
+    pub fn url(&self, client: &MsClient) -> String {
+        match self {
+            Self::Id(id) => client.video_url(id),
+            Self::Url(url) => url.clone(),
+        }
+    }
+
+    /// The default file name of the video: `<id>.mp4`, or the last segment of its URL.
+    pub fn file_name(&self) -> Result<String, String> {
+        match self {
+            Self::Id(id) => Ok(format!("{id}.mp4")),
+            Self::Url(url) => video_file_name(url),
+        }
+    }
+}
+
+/// The default file name of a video, from the last segment of its URL.
+///
+/// Only plain names are used, so that the URL can't pick a path outside the current directory.
+fn video_file_name(url: &str) -> Result<String, String> {
+    let name = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            url.path_segments()?
+                .rev()
+                .find(|segment| !segment.is_empty())
+                .map(str::to_string)
+        })
+        .unwrap_or_default();
+    let stem = name.strip_suffix(".mp4").unwrap_or(&name);
+    if is_plain_name(stem) {
+        Ok(format!("{stem}.mp4"))
+    } else {
+        Err(format!(
+            "can't name the video after '{url}', pass the path to save it to"
+        ))
+    }
+}
+
+/// Writes the video next to `path` and renames it, so that a failed write leaves no partial file.
+pub fn write_video(path: &Path, video: &[u8]) -> std::io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(".{}.tmp", std::process::id()));
+    std::fs::write(&temp, video)
+        .and_then(|_| std::fs::rename(&temp, path))
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&temp);
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_videos_after_their_url() {
+        assert_eq!(
+            video_file_name("https://cdn.borrow.dev/ms/abc123.mp4?token=1").unwrap(),
+            "abc123.mp4"
+        );
+        assert_eq!(
+            video_file_name("https://videos.example.com/abc-123/").unwrap(),
+            "abc-123.mp4"
+        );
+    }
+
+    #[test]
+    fn parses_ids_and_urls() {
+        assert_eq!(
+            parse_video_target("abc_123").unwrap(),
+            VideoTarget::Id("abc_123".to_string())
+        );
+        assert_eq!(
+            parse_video_target("https://videos.example.com/abc.mp4").unwrap(),
+            VideoTarget::Url("https://videos.example.com/abc.mp4".to_string())
+        );
+        for value in [
+            "",
+            "../../x",
+            "a/b",
+            "a.mp4",
+            "ftp://example.com/a.mp4",
+            "https://",
+        ] {
+            assert!(parse_video_target(value).is_err(), "{value}");
+        }
+        assert_eq!(
+            VideoTarget::Id("abc".to_string()).file_name().unwrap(),
+            "abc.mp4"
+        );
+    }
+
+    #[test]
+    fn rejects_names_that_are_not_plain() {
+        assert!(video_file_name("https://borrow.dev/").is_err());
+        assert!(video_file_name("https://borrow.dev/%2E%2E%2Fx.mp4").is_err());
+        assert!(video_file_name("https://borrow.dev/.mp4").is_err());
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{IsTerminal, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use borrow_dev::MsError;
+use chrono::{DateTime, Utc};
+use clap::Args;
+
+use super::{
+    PreparedRequest, RegionRow, RequestArgs, delta_percent, error_exit_code, execute_request,
+    parse_duration, parse_regions, region_flag, region_to_json, with_progress,
+};
+
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+    /// Time between the start of two measurements, e.g. "30s" or "5m"
+    #[arg(long = "every", value_parser = parse_duration, default_value = "60s")]
+    pub every: Duration,
+
+    /// Highlight regions whose p50 changed by more than this percentage since the previous sample
+    #[arg(long = "highlight-change", value_parser = parse_percent, default_value = "10%")]
+    pub highlight_change: f64,
+
+    /// Append every sample as a line of JSON to this file
+    #[arg(long = "ndjson")]
+    pub ndjson: Option<PathBuf>,
+
+    /// Stop after this many samples (runs until Ctrl-C by default)
+    #[arg(long = "count")]
+    pub count: Option<u64>,
+}
+
+/// Parses a percentage such as "15%" or "15".
+pub fn parse_percent(value: &str) -> Result<f64, String> {
+    let number = value.trim().trim_end_matches('%').trim();
+    match number.parse::<f64>() {
+        Ok(percent) if percent.is_finite() && percent >= 0.0 => Ok(percent),
+        _ => Err(format!(
+            "invalid percentage '{value}', expected a positive number such as '15%'"
+        )),
+    }
+}
+
+/// A measurement taken by `borrow ms watch`.
+struct Sample {
+    number: u64,
+    timestamp: DateTime<Utc>,
+    outcome: Result<Vec<RegionRow>, MsError>,
+}
+
+struct Watch<'a> {
+    request: &'a PreparedRequest,
+    args: &'a WatchArgs,
+    verbose: bool,
+    /// Region codes in the order they were first seen, so rows don't jump around between samples.
+    order: Vec<String>,
+    previous: Option<Vec<RegionRow>>,
+    current: Option<Vec<RegionRow>>,
+    last_error: Option<(DateTime<Utc>, String)>,
+    ndjson: Option<File>,
+}
+
+impl Watch<'_> {
+    fn record(&mut self, sample: Sample) {
+        if let Some(file) = &mut self.ndjson {
+            let line = sample_to_json(self.request, &sample);
+            if let Err(e) = writeln!(file, "{line}") {
+                eprintln!("Warning: failed to append the sample to the NDJSON file: {e}");
+            }
+        }
+
+        match sample.outcome {
+            Ok(regions) => {
+                for region in &regions {
+                    if !self.order.contains(&region.code) {
+                        self.order.push(region.code.clone());
+                    }
+                }
+                self.previous = self.current.replace(regions);
+                self.last_error = None;
+            }
+            Err(e) => self.last_error = Some((sample.timestamp, e.to_string())),
+        }
+    }
+
+    fn draw(&self, sample: u64, next: Option<DateTime<Utc>>) {
+        let color = std::io::stdout().is_terminal();
+        if color {
+            // Clear the screen and move the cursor home, so the table is redrawn in place.
+            print!("\x1b[2J\x1b[H");
+        }
+
+        println!(
+            "================================================================================="
+        );
+        println!(
+            "👀 Watching {} {} every {}",
+            self.request.method,
+            self.request.url,
+            humantime::format_duration(self.args.every)
+        );
+        println!(
+            "Sample {sample}, highlighting p50 changes above {}%",
+            self.args.highlight_change
+        );
+        println!();
+
+        let code_header = if self.verbose {
+            format!("{:<26} ", "CODE")
+        } else {
+            String::new()
+        };
+        println!(
+            "{:<34} {code_header}{:>10} {:>10} {:>10} {:>10} {:>10}",
+            "🌎 REGION", "ITERATIONS", "p50 (ms)", "CHANGE", "p90 (ms)", "p99 (ms)"
+        );
+        println!("{}", "-".repeat(if self.verbose { 116 } else { 89 }));
+
+        let current = self.current.as_deref().unwrap_or_default();
+        for (i, code) in self.order.iter().enumerate() {
+            let Some(region) = current.iter().find(|r| &r.code == code) else {
+                continue;
+            };
+            let label = format!(
+                "{} {}  {} ({})",
+                i + 1,
+                region_flag(&region.country),
+                region.country,
+                region.region
+            );
+            let code = if self.verbose {
+                format!("{:<26} ", region.code)
+            } else {
+                String::new()
+            };
+
+            let change = self
+                .previous
+                .as_deref()
+                .and_then(|previous| previous.iter().find(|r| r.code == region.code))
+                .and_then(|previous| delta_percent(previous.p50, region.p50));
+            let highlighted = change.filter(|c| c.abs() > self.args.highlight_change);
+            let change_label = match (change, highlighted) {
+                (Some(c), Some(_)) if c > 0.0 => format!("▲ {c:+.1}%"),
+                (Some(c), Some(_)) => format!("▼ {c:+.1}%"),
+                (Some(c), None) => format!("{c:+.1}%"),
+                (None, _) => "-".to_string(),
+            };
+
+            let row = format!(
+                "{:<34} {code}{:>10} {:>10.2} {:>10} {:>10.2} {:>10.2}",
+                label,
+                region.iterations_label(),
+                region.p50,
+                change_label,
+                region.p90,
+                region.p99
+            );
+            match highlighted {
+                // Red when the region got slower, green when it got faster.
+                Some(c) if color => {
+                    let code = if c > 0.0 { 31 } else { 32 };
+                    println!("\x1b[{code}m{row}\x1b[0m");
+                }
+                _ => println!("{row}"),
+            }
+        }
+
+        println!();
+        println!(
+            "================================================================================="
+        );
+        if let Some((timestamp, error)) = &self.last_error {
+            println!(
+                "Error at {}: {error}",
+                timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+            );
+        }
+        match next {
+            Some(next) => println!(
+                "Next sample at {}, press Ctrl-C to stop.",
+                next.format("%H:%M:%S UTC")
+            ),
+            None => println!("Done."),
+        }
+        println!();
+    }
+}
+
+fn sample_to_json(request: &PreparedRequest, sample: &Sample) -> serde_json::Value {
+    let mut value = serde_json::json!({
+        "timestamp": sample.timestamp.to_rfc3339(),
+        "sample": sample.number,
+        "method": request.method,
+        "url": request.url,
+        "requestHash": request.hash(),
+    });
+    match &sample.outcome {
+        Ok(regions) => {
+            value["regions"] = regions.iter().map(region_to_json).collect();
+        }
+        Err(e) => {
+            value["error"] = serde_json::json!({
+                "code": e.code(),
+                "message": e.to_string(),
+            });
+        }
+    }
+    value
+}
+
+/// Measures the request every `args.every` until cancelled, redrawing the results after each sample.
+pub fn run_watch(
+    request: &PreparedRequest,
+    request_args: &RequestArgs,
+    args: &WatchArgs,
+    verbose: bool,
+) {
+    let ndjson = match &args.ndjson {
+        Some(path) => match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("Error: failed to open '{}': {e}", path.display());
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let mut watch = Watch {
+        request,
+        args,
+        verbose,
+        order: Vec::new(),
+        previous: None,
+        current: None,
+        last_error: None,
+        ndjson,
+    };
+
+    let message = format!("Measuring {} {}", request.method, request.url);
+    let mut number = 0;
+    loop {
+        number += 1;
+        let start = Instant::now();
+        let timestamp = Utc::now();
+        let outcome = with_progress(&message, || execute_request(request_args, request))
+            .map(|response| parse_regions(&response));
+
+        if let Err(e) = &outcome
+            && e.is_fatal()
+        {
+            eprintln!("Error: {e}");
+            std::process::exit(error_exit_code(e));
+        }
+
+        watch.record(Sample {
+            number,
+            timestamp,
+            outcome,
+        });
+
+        if args.count.is_some_and(|count| number >= count) {
+            watch.draw(number, None);
+            break;
+        }
+
+        let wait = args.every.saturating_sub(start.elapsed());
+        let next = Utc::now() + wait;
+        watch.draw(number, Some(next));
+        std::thread::sleep(wait);
+    }
+}
@@ -1,32 +1,43 @@
-use clap::Parser;
-use emojic::country_flag;
-use std::{collections::HashMap, time::Duration};
-
-#[cfg(not(debug_assertions))]
-static ENDPOINT: &str = "https://api.borrow.dev";
-#[cfg(debug_assertions)]
-static ENDPOINT: &str = "http://localhost:8787";
+pub mod lib;
+use borrow_dev::{MeasureResponse, MsClient, MsError};
+use clap::{Parser, Subcommand};
+use lib::{
+    ApiArgs, BaselineArgs, BaselineOutcome, BatchExpansion, CiArgs, CiRun, ClientArgs, ComparedUrl,
+    DEFAULT_SCENARIO_FILE, ExportTarget, ExportedRun, HistoryRecord, LOCAL_ITERATIONS, MsReport,
+    OutputFormat, Percentile, PreparedRequest, RegionFilter, ReportBaseline, RequestArgs,
+    RequestBody, THRESHOLD_EXIT_CODE, Thresholds, VideoTarget, WatchArgs, apply_publish_options,
+    batch_exit_code, check_assertions, check_baseline, check_batch_assertions, ci_regions,
+    enforce_thresholds, error_exit_code, execute_request, expand_har, expand_openapi,
+    export_results, find_history_record, install_cancel_handler, live_dashboard, load_baseline,
+    load_history, load_latest_measurement, load_scenarios, measure_breakdown, open_dashboard,
+    parse_curl_command, parse_duration, parse_export, parse_regions, parse_video_target,
+    placement_regions, prepare_request, print_batch_report, print_comparison, print_diff,
+    print_history, print_regions, print_report, print_scenario_report, read_curl_command,
+    redact_headers, reject_assertions, report_to_ci, resolve_regions, run_batch, run_scenarios,
+    run_watch, save_baseline, save_history_record, wait_for_video, with_progress,
+    write_html_report, write_video,
+};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct MsCommand {
-    /// The API URL to measure latency for
-    pub url: String,
+    #[command(subcommand)]
+    pub command: Option<MsSubcommand>,
 
-    /// HTTP method (GET, POST, PUT, DELETE, PATCH, OPTIONS, HEAD)
-    #[arg(short = 'm', long = "method", default_value = "GET")]
-    pub method: String,
-
-    /// Borrow API key (defaults to BORROW_API_KEY env var)
-    #[arg(short = 'k', long = "api-key", env = "BORROW_API_KEY")]
-    pub api_key: String,
+    /// The API URL to measure latency for
+    #[arg(required_unless_present = "from_curl")]
+    pub url: Option<String>,
 
-    /// Headers to forward in the request (repeatable, format: "Key: Value")
-    #[arg(long = "header", num_args = 0..)]
-    pub header: Vec<String>,
+    /// Import the URL, method, headers and body from a curl command line ("-" reads it from stdin)
+    #[arg(long = "from-curl", conflicts_with_all = ["url", "method", "body", "form", "form_file"])]
+    pub from_curl: Option<String>,
 
-    /// Request body (for POST, PUT, DELETE)
-    #[arg(short = 'd', long = "body")]
-    pub body: Option<String>,
+    #[command(flatten)]
+    pub request: RequestArgs,
 
     /// Publish parts of the request (comma-separated: body,query,headers)
     #[arg(short = 'p', long = "publish", value_delimiter = ',')]
@@ -40,16 +51,390 @@ pub struct MsCommand {
     #[arg(long = "no-video", default_value_t = false)]
     pub no_video: bool,
 
+    /// Download the video of the published measurement to this file once it is rendered
+    #[arg(long = "save-video", conflicts_with_all = ["no_video", "local"])]
+    pub save_video: Option<PathBuf>,
+
+    /// Maximum time to wait for the video to be rendered
+    #[arg(long = "video-timeout", value_parser = parse_duration, default_value = "5m", requires = "save_video")]
+    pub video_timeout: Duration,
+
     /// Show region codes in output
     #[arg(short = 'v', long = "verbose")]
     pub verbose: bool,
+
+    /// Also time the DNS, connect, TLS, TTFB and download phases of the request from this machine
+    #[arg(long = "breakdown", default_value_t = false)]
+    pub breakdown: bool,
+
+    /// Output format (the banner and footer are only printed for 'table')
+    #[arg(short = 'o', long = "output", value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+
+    /// Don't save this run to the local history
+    #[arg(long = "no-history", default_value_t = false)]
+    pub no_history: bool,
+
+    /// Also write the results to a self-contained HTML file, with credentials in headers redacted
+    #[arg(long = "report")]
+    pub report: Option<PathBuf>,
+
+    /// Compare the HTML report with a saved measurement (`-o json` output, a history run or an ms API response)
+    #[arg(long = "report-compare", requires = "report")]
+    pub report_compare: Option<PathBuf>,
+
+    /// Export the results to monitoring, "prometheus=<path>" or "otlp=<collector URL>" (repeatable)
+    #[arg(long = "export", value_parser = parse_export)]
+    pub export: Vec<ExportTarget>,
+
+    #[command(flatten)]
+    pub thresholds: Thresholds,
+
+    #[command(flatten)]
+    pub baseline: BaselineArgs,
+
+    #[command(flatten)]
+    pub ci: CiArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MsSubcommand {
+    /// List the regions latency can be measured from
+    #[command(name = "regions")]
+    Regions {
+        #[command(flatten)]
+        region_filter: RegionFilter,
+
+        /// Output format
+        #[arg(short = 'o', long = "output", value_enum, default_value_t = OutputFormat::Table)]
+        output: OutputFormat,
+    },
+
+    /// List past runs saved in the local history
+    #[command(name = "history")]
+    History {
+        /// Only list runs for this API URL
+        url: Option<String>,
+
+        /// Maximum number of runs to list, most recent first
+        #[arg(short = 'n', long = "limit", default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Show per-region latency changes between two past runs
+    #[command(name = "diff")]
+    Diff {
+        /// ID (or unique ID prefix) of the older run
+        run_a: String,
+
+        /// ID (or unique ID prefix) of the newer run
+        run_b: String,
+    },
+
+    /// Measure several API URLs with the same request and compare them region by region
+    #[command(name = "compare")]
+    Compare {
+        /// The API URLs to compare, the first one is the reference for the deltas
+        #[arg(required = true, num_args = 2..=26)]
+        urls: Vec<String>,
+
+        #[command(flatten)]
+        request: RequestArgs,
+
+        /// The percentile to compare
+        #[arg(long = "percentile", value_enum, default_value_t = Percentile::P50)]
+        percentile: Percentile,
+
+        /// Output format
+        #[arg(short = 'o', long = "output", value_enum, default_value_t = OutputFormat::Table)]
+        output: OutputFormat,
+    },
+
+    /// Measure every request of an OpenAPI document or a HAR file
+    #[command(name = "batch")]
+    Batch {
+        /// OpenAPI 3 document (YAML or JSON), requests are built from the examples it contains
+        #[arg(
+            long = "openapi",
+            required_unless_present = "har",
+            conflicts_with_all = ["har", "method", "body", "form", "form_file"]
+        )]
+        openapi: Option<PathBuf>,
+
+        /// HAR file, e.g. exported from the browser's developer tools
+        #[arg(
+            long = "har",
+            conflicts_with_all = ["method", "body", "form", "form_file", "base_url"]
+        )]
+        har: Option<PathBuf>,
+
+        /// Base URL for the OpenAPI paths (defaults to the first server of the document)
+        #[arg(long = "base-url")]
+        base_url: Option<String>,
+
+        /// HTTP methods to measure (comma-separated), requests with other methods are skipped
+        #[arg(long = "methods", value_delimiter = ',', default_value = "GET")]
+        methods: Vec<String>,
+
+        /// Maximum number of requests measured at the same time
+        #[arg(long = "concurrency", default_value_t = 4)]
+        concurrency: usize,
+
+        #[command(flatten)]
+        request: RequestArgs,
+
+        /// Output format
+        #[arg(short = 'o', long = "output", value_enum, default_value_t = OutputFormat::Table)]
+        output: OutputFormat,
+    },
+
+    /// Measure the scenarios of a scenario file and check their latency thresholds
+    #[command(name = "run")]
+    Run {
+        /// Scenario file (TOML) listing the requests to measure
+        #[arg(default_value = DEFAULT_SCENARIO_FILE)]
+        file: PathBuf,
+
+        /// Only run the scenarios with these names (comma-separated)
+        #[arg(long = "only", value_delimiter = ',')]
+        only: Vec<String>,
+
+        #[command(flatten)]
+        client: ClientArgs,
+
+        /// Output format
+        #[arg(short = 'o', long = "output", value_enum, default_value_t = OutputFormat::Table)]
+        output: OutputFormat,
+
+        /// Don't save these runs to the local history
+        #[arg(long = "no-history", default_value_t = false)]
+        no_history: bool,
+
+        /// Export the results to monitoring, "prometheus=<path>" or "otlp=<collector URL>" (repeatable)
+        #[arg(long = "export", value_parser = parse_export)]
+        export: Vec<ExportTarget>,
+
+        #[command(flatten)]
+        ci: CiArgs,
+    },
+
+    /// Browse measurements in a full-screen dashboard, measured live or from a saved JSON file
+    #[command(
+        name = "dashboard",
+        mut_arg("api_key", |arg| arg.required_unless_present_any(["local", "open"]))
+    )]
+    Dashboard {
+        /// The API URL to measure periodically
+        #[arg(required_unless_present = "open", conflicts_with = "open")]
+        url: Option<String>,
+
+        /// Open a saved file instead: `-o json` output, a history run, an ms API response or `watch --ndjson` samples
+        #[arg(long = "open")]
+        open: Option<PathBuf>,
+
+        /// Time between two live measurements
+        #[arg(long = "every", value_parser = parse_duration, default_value = "60s")]
+        every: Duration,
+
+        #[command(flatten)]
+        request: RequestArgs,
+    },
+
+    /// Download the video of a published measurement
+    #[command(name = "video")]
+    Video {
+        /// ID of the published measurement (the end of its share URL), or its video URL
+        #[arg(value_parser = parse_video_target)]
+        video: VideoTarget,
+
+        /// Where to save the video (defaults to "<id>.mp4", or the last segment of the URL)
+        path: Option<PathBuf>,
+
+        #[command(flatten)]
+        api: ApiArgs,
+    },
+
+    /// Measure an API URL periodically and show how its latency changes
+    #[command(name = "watch")]
+    Watch {
+        /// The API URL to measure latency for
+        url: String,
+
+        #[command(flatten)]
+        request: RequestArgs,
+
+        #[command(flatten)]
+        watch: WatchArgs,
+
+        /// Show region codes in output
+        #[arg(short = 'v', long = "verbose")]
+        verbose: bool,
+    },
 }
 
 pub fn handle_ms_command(cmd: MsCommand) {
-    let method = cmd.method.to_uppercase();
-    match method.as_str() {
-        "GET" | "POST" | "PUT" | "DELETE" | "PATCH" | "OPTIONS" | "HEAD" => {}
-        _ => {
+    install_cancel_handler();
+    match cmd.command {
+        Some(MsSubcommand::Regions {
+            region_filter,
+            output,
+        }) => handle_regions_command(region_filter, output),
+        Some(MsSubcommand::History { url, limit }) => handle_history_command(url, limit),
+        Some(MsSubcommand::Diff { run_a, run_b }) => handle_diff_command(&run_a, &run_b),
+        Some(MsSubcommand::Compare {
+            urls,
+            request,
+            percentile,
+            output,
+        }) => handle_compare_command(&urls, &request, percentile, output),
+        Some(MsSubcommand::Batch {
+            openapi,
+            har,
+            base_url,
+            methods,
+            concurrency,
+            request,
+            output,
+        }) => handle_batch_command(
+            openapi,
+            har,
+            base_url,
+            &methods,
+            concurrency,
+            &request,
+            output,
+        ),
+        Some(MsSubcommand::Run {
+            file,
+            only,
+            client,
+            output,
+            no_history,
+            export,
+            ci,
+        }) => handle_run_command(&file, &only, &client, output, no_history, &export, &ci),
+        Some(MsSubcommand::Dashboard {
+            url,
+            open,
+            every,
+            request,
+        }) => handle_dashboard_command(url, open, every, request),
+        Some(MsSubcommand::Watch {
+            url,
+            request,
+            watch,
+            verbose,
+        }) => handle_watch_command(&url, &request, &watch, verbose),
+        Some(MsSubcommand::Video { video, path, api }) => handle_video_command(&video, path, &api),
+        None => handle_measure_command(cmd),
+    }
+}
+
+fn handle_regions_command(region_filter: RegionFilter, output: OutputFormat) {
+    let regions = match resolve_regions(&region_filter) {
+        Ok(Some(regions)) => regions,
+        Ok(None) => placement_regions(),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    print_regions(&regions, output);
+}
+
+fn handle_history_command(url: Option<String>, limit: usize) {
+    let mut records: Vec<HistoryRecord> = load_history()
+        .into_iter()
+        .filter(|r| url.as_ref().is_none_or(|url| &r.url == url))
+        .collect();
+    records.reverse();
+    records.truncate(limit);
+    print_history(&records);
+}
+
+fn handle_diff_command(run_a: &str, run_b: &str) {
+    let (a, b) = match (find_history_record(run_a), find_history_record(run_b)) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    print_diff(&a, &b);
+}
+
+fn handle_compare_command(
+    urls: &[String],
+    args: &RequestArgs,
+    percentile: Percentile,
+    output: OutputFormat,
+) {
+    let requests: Vec<PreparedRequest> = urls
+        .iter()
+        .map(|url| {
+            let mut request = prepare_request(url, args);
+            request.params.enable_video = Some(false);
+            request.params.scope = Some("private".to_string());
+            request
+        })
+        .collect();
+
+    // Measure every URL at the same time so they see the same network conditions.
+    let message = format!("Measuring {} URLs", requests.len());
+    let results: Vec<Result<MeasureResponse, MsError>> = with_progress(&message, || {
+        std::thread::scope(|s| {
+            let handles: Vec<_> = requests
+                .iter()
+                .map(|request| s.spawn(|| execute_request(args, request)))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        })
+    });
+
+    let mut compared = Vec::new();
+    for (request, result) in requests.iter().zip(results) {
+        match result {
+            Ok(response) => compared.push(ComparedUrl {
+                url: request.url.clone(),
+                regions: parse_regions(&response),
+            }),
+            Err(e) => {
+                eprintln!("Error: {} ({})", e, request.url);
+                std::process::exit(error_exit_code(&e));
+            }
+        }
+    }
+
+    print_comparison(&requests[0].method, &compared, percentile, output);
+
+    let mut failed_assertions = false;
+    for compared in &compared {
+        if compared
+            .regions
+            .iter()
+            .any(|r| r.assertion_failures.is_some())
+        {
+            eprintln!("{}:", compared.url);
+            failed_assertions |= check_assertions(&compared.regions);
+        }
+    }
+    if failed_assertions {
+        std::process::exit(THRESHOLD_EXIT_CODE);
+    }
+}
+
+fn handle_batch_command(
+    openapi: Option<PathBuf>,
+    har: Option<PathBuf>,
+    base_url: Option<String>,
+    methods: &[String],
+    concurrency: usize,
+    args: &RequestArgs,
+    output: OutputFormat,
+) {
+    let valid_methods = ["GET", "POST", "PUT", "DELETE", "PATCH", "OPTIONS", "HEAD"];
+    for method in methods {
+        if !valid_methods.contains(&method.to_uppercase().as_str()) {
             eprintln!(
                 "Error: unsupported HTTP method '{}'. Use GET, POST, PUT, DELETE, PATCH, OPTIONS, or HEAD.",
                 method
@@ -58,204 +443,382 @@ pub fn handle_ms_command(cmd: MsCommand) {
         }
     }
 
-    let mut headers: HashMap<String, String> = HashMap::new();
-    for h in &cmd.header {
-        if let Some((key, value)) = h.split_once(':') {
-            headers.insert(key.trim().to_string(), value.trim().to_string());
-        } else {
-            eprintln!(
-                "Error: invalid header format '{}'. Expected 'Key: Value'.",
-                h
-            );
+    let expansion = match (openapi, har) {
+        (Some(path), _) => expand_openapi(&path, base_url.as_deref(), methods),
+        (None, Some(path)) => expand_har(&path, methods),
+        (None, None) => unreachable!("clap requires --openapi or --har"),
+    };
+    let BatchExpansion { items, warnings } = match expansion {
+        Ok(expansion) => expansion,
+        Err(e) => {
+            eprintln!("Error: {e}");
             std::process::exit(1);
         }
+    };
+
+    for warning in &warnings {
+        eprintln!("Warning: {warning}");
+    }
+    if items.is_empty() {
+        eprintln!(
+            "Error: no request to measure, check the --methods filter (currently {}).",
+            methods.join(",")
+        );
+        std::process::exit(1);
     }
 
-    let mut measure_request = serde_json::Map::new();
-    measure_request.insert(
-        "url".to_string(),
-        serde_json::Value::String(cmd.url.clone()),
-    );
-    measure_request.insert(
-        "method".to_string(),
-        serde_json::Value::String(method.clone()),
-    );
-    measure_request.insert(
-        "headers".to_string(),
-        serde_json::Value::Object(
-            headers
-                .into_iter()
-                .map(|(k, v)| (k, serde_json::Value::String(v)))
-                .collect(),
-        ),
-    );
+    let message = format!("Measuring {} request(s)", items.len());
+    let mut results = with_progress(&message, || run_batch(items, args, concurrency));
+    print_batch_report(&mut results, output);
 
-    if let Some(body) = &cmd.body {
-        if method == "GET" {
-            eprintln!("Error: --body cannot be used with GET requests.");
+    check_batch_assertions(&results);
+    let code = batch_exit_code(&results);
+    let failed = results.iter().filter(|r| r.outcome.is_err()).count();
+    if failed > 0 {
+        eprintln!("Error: {failed} of {} request(s) failed.", results.len());
+    }
+    if code != 0 {
+        std::process::exit(code);
+    }
+}
+
+fn handle_run_command(
+    file: &Path,
+    only: &[String],
+    client: &ClientArgs,
+    output: OutputFormat,
+    no_history: bool,
+    export: &[ExportTarget],
+    ci: &CiArgs,
+) {
+    let mut scenarios = match load_scenarios(file) {
+        Ok(scenarios) => scenarios,
+        Err(e) => {
+            eprintln!("Error: {e}");
             std::process::exit(1);
         }
-        measure_request.insert("body".to_string(), serde_json::Value::String(body.clone()));
+    };
+
+    if !only.is_empty() {
+        if let Some(name) = only
+            .iter()
+            .find(|name| !scenarios.iter().any(|s| &s.name == *name))
+        {
+            eprintln!("Error: unknown scenario '{name}' in '{}'.", file.display());
+            std::process::exit(1);
+        }
+        scenarios.retain(|s| only.contains(&s.name));
     }
 
-    let mut payload = serde_json::json!({
-        "action": "measure",
-        "enableVideo": !cmd.no_video,
-        "measureRequest": measure_request,
-    });
+    let results = run_scenarios(scenarios, client, !no_history);
+    print_scenario_report(&results, output);
+
+    let runs: Vec<ExportedRun> = results
+        .iter()
+        .filter_map(|result| {
+            let run = result.outcome.as_ref().ok()?;
+            Some(ExportedRun {
+                scenario: Some(&result.scenario.name),
+                method: &result.scenario.method,
+                url: &result.scenario.url,
+                regions: &run.regions,
+            })
+        })
+        .collect();
+    export_or_exit(export, &runs);
+
+    let ci_runs: Vec<CiRun> = results
+        .iter()
+        .map(|result| CiRun {
+            name: result.scenario.name.clone(),
+            outcome: result
+                .outcome
+                .as_ref()
+                .map(|run| ci_regions(&run.regions, &run.violations, &[])),
+        })
+        .collect();
+    report_to_ci_or_exit(ci, &ci_runs);
+
+    if let Some(e) = results.iter().find_map(|r| r.outcome.as_ref().err()) {
+        std::process::exit(error_exit_code(e));
+    }
+    if results.iter().any(|r| r.status() == "failed") {
+        std::process::exit(THRESHOLD_EXIT_CODE);
+    }
+}
+
+fn handle_dashboard_command(
+    url: Option<String>,
+    open: Option<PathBuf>,
+    every: Duration,
+    args: RequestArgs,
+) {
+    let url = match (open, url) {
+        (Some(path), _) => return open_dashboard(&path),
+        (None, Some(url)) => url,
+        (None, None) => unreachable!("clap requires a URL or --open"),
+    };
+    reject_assertions(&args.assertions, "dashboard");
+    let mut request = prepare_request(&url, &args);
+    request.params.enable_video = Some(false);
+    request.params.scope = Some("private".to_string());
+    live_dashboard(request, args, every);
+}
+
+fn handle_watch_command(url: &str, args: &RequestArgs, watch: &WatchArgs, verbose: bool) {
+    reject_assertions(&args.assertions, "watch");
+    let mut request = prepare_request(url, args);
+    // Samples aren't published, so there is no video to generate.
+    request.params.enable_video = Some(false);
+    request.params.scope = Some("private".to_string());
+    run_watch(&request, args, watch, verbose);
+}
 
-    if !cmd.publish.is_empty() {
-        let valid_parts = ["body", "headers", "query"];
-        for part in &cmd.publish {
-            if !valid_parts.contains(&part.as_str()) {
-                eprintln!(
-                    "Error: invalid publish part '{}'. Valid options: body, headers, query.",
-                    part
-                );
+fn handle_video_command(video: &VideoTarget, path: Option<PathBuf>, api: &ApiArgs) {
+    let path = match path {
+        Some(path) => path,
+        None => match video.file_name() {
+            Ok(name) => PathBuf::from(name),
+            Err(e) => {
+                eprintln!("Error: {e}");
                 std::process::exit(1);
             }
+        },
+    };
+    let client = api.client();
+    let url = video.url(&client);
+    let message = format!("Downloading the video to {}", path.display());
+    match with_progress(&message, || client.fetch_video(&url)) {
+        Ok(video) => write_video_or_exit(&path, &video),
+        Err(e) => {
+            eprintln!("Error: failed to download the video: {e}");
+            std::process::exit(error_exit_code(&e));
         }
-        payload["publish"] = serde_json::to_value(&cmd.publish).unwrap();
     }
+}
 
-    payload["scope"] = serde_json::Value::String(cmd.scope.clone());
-
-    let client = reqwest::blocking::Client::new();
-    let res = client
-        .post(&format!("{ENDPOINT}/v1/ms"))
-        .header("Content-Type", "application/json")
-        .header("X-Borrow-Api-Key", &cmd.api_key)
-        .timeout(Duration::from_mins(3))
-        .json(&payload)
-        .send();
+/// Downloads the video of a measurement that was just published to `path`, exiting when it failed.
+fn save_video_or_exit(client: &MsClient, url: &str, path: &Path, timeout: Duration) {
+    let message = format!("Downloading the video to {}", path.display());
+    let video = with_progress(&message, || wait_for_video(client, url, timeout));
+    let video = match video {
+        Ok(video) => video,
+        Err(MsError::Timeout(_)) => {
+            eprintln!(
+                "Error: the video wasn't rendered after {}, try again later with `borrow ms video {url}`",
+                humantime::format_duration(timeout)
+            );
+            std::process::exit(error_exit_code(&MsError::Timeout(timeout)));
+        }
+        Err(e) => {
+            eprintln!("Error: failed to download the video: {e}");
+            std::process::exit(error_exit_code(&e));
+        }
+    };
+    write_video_or_exit(path, &video);
+}
 
-    match res {
-        Ok(response) => {
-            let status = response.status();
-            let body_text = response.text().unwrap_or_default();
+fn write_video_or_exit(path: &Path, video: &[u8]) {
+    if let Err(e) = write_video(path, video) {
+        eprintln!(
+            "Error: failed to write the video to '{}': {e}",
+            path.display()
+        );
+        std::process::exit(1);
+    }
+    eprintln!("Video saved to {}", path.display());
+}
 
-            if !status.is_success() {
-                eprintln!("Error ({}): {}", status, body_text);
-                std::process::exit(1);
-            }
+/// Reports the runs to CI, exiting when the JUnit report couldn't be written.
+fn report_to_ci_or_exit(args: &CiArgs, runs: &[CiRun]) {
+    if let Err(e) = report_to_ci(args, runs) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
 
-            let parsed: serde_json::Value = match serde_json::from_str(&body_text) {
-                Ok(v) => v,
-                Err(_) => {
-                    eprintln!("Error: failed to parse response: {}", body_text);
-                    std::process::exit(1);
-                }
-            };
+/// Exports the runs to every target, exiting when any of them failed.
+fn export_or_exit(targets: &[ExportTarget], runs: &[ExportedRun]) {
+    let mut failed = false;
+    for target in targets {
+        if let Err(e) = export_results(target, runs) {
+            eprintln!("Error: {e}");
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
 
-            if parsed["result"] == "error" {
-                eprintln!(
-                    "Error [{}]: {}",
-                    parsed["error"].as_str().unwrap_or("UNKNOWN"),
-                    parsed["message"].as_str().unwrap_or("Unknown error")
-                );
+fn handle_measure_command(mut cmd: MsCommand) {
+    if let Some(value) = &cmd.from_curl {
+        let curl = match read_curl_command(value).and_then(|c| parse_curl_command(&c)) {
+            Ok(curl) => curl,
+            Err(e) => {
+                eprintln!("Error: {e}");
                 std::process::exit(1);
             }
+        };
+        for warning in &curl.warnings {
+            eprintln!("Warning: {warning}");
+        }
+        cmd.url = Some(curl.url);
+        cmd.request.method = curl.method;
+        // Headers given with --header are applied last, so they override the imported ones.
+        cmd.request.header.splice(0..0, curl.headers);
+        cmd.request.body = curl.body.map(RequestBody::inline);
+    }
 
-            println!(
-                "================================================================================="
-            );
-            println!("🏁 API latency stats for {} {}", method, cmd.url);
-            if cmd.verbose {
-                println!(
-                    "Request body hash: {:x}",
-                    md5::compute(&serde_json::to_string(&measure_request).unwrap())
-                );
-            }
-            println!();
-
-            let latency = &parsed["latency"];
-            if let Some(obj) = latency.as_object() {
-                if cmd.verbose {
-                    println!(
-                        "{:<34} {:<26} {:>10} {:>10} {:>10} {:>10}",
-                        "🌎 REGION", "CODE", "ITERATIONS", "p50 (ms)", "p90 (ms)", "p99 (ms)"
-                    );
-                    println!("{}", "-".repeat(104));
-                } else {
-                    println!(
-                        "{:<34} {:>10} {:>10} {:>10} {:>10}",
-                        "🌎 REGION", "ITERATIONS", "p50 (ms)", "p90 (ms)", "p99 (ms)"
-                    );
-                    println!("{}", "-".repeat(78));
-                }
+    let url = cmd.url.expect("URL is required");
+    let mut request = prepare_request(&url, &cmd.request);
 
-                let mut regions: Vec<_> = obj.iter().collect();
-                regions.sort_by(|a, b| {
-                    let p50_a = a.1["data"]["p50"].as_f64().unwrap_or(f64::MAX);
-                    let p50_b = b.1["data"]["p50"].as_f64().unwrap_or(f64::MAX);
-                    p50_a
-                        .partial_cmp(&p50_b)
-                        .unwrap_or(std::cmp::Ordering::Equal)
-                });
-
-                let mut i = 1;
-                for (region_code, entry) in regions {
-                    let country = entry["metadata"]["country"].as_str().unwrap_or("??");
-                    let region_name = entry["metadata"]["region"].as_str().unwrap_or("Unknown");
-                    let flag = country_flag(country);
-                    let label = format!("{} {}  {} ({})", i, flag, country, region_name);
-
-                    let amount = entry["data"]["amount"].as_u64().unwrap_or(0);
-                    let p50 = entry["data"]["p50"].as_f64().unwrap_or(0.0);
-                    let p90 = entry["data"]["p90"].as_f64().unwrap_or(0.0);
-                    let p99 = entry["data"]["p99"].as_f64().unwrap_or(0.0);
-
-                    if cmd.verbose {
-                        println!(
-                            "{:<34} {:<26} {:>10} {:>10.2} {:>10.2} {:>10.2}",
-                            label, region_code, amount, p50, p90, p99
-                        );
-                    } else {
-                        println!(
-                            "{:<34} {:>10} {:>10.2} {:>10.2} {:>10.2}",
-                            label, amount, p50, p90, p99
-                        );
-                    }
-                    i += 1;
+    // Read the comparisons before measuring, so that a wrong path doesn't waste a measurement.
+    let baseline = cmd.baseline.baseline.as_ref().map(|path| {
+        load_baseline(path).unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        })
+    });
+    let report_baseline =
+        cmd.report_compare
+            .as_ref()
+            .map(|path| match load_latest_measurement(path) {
+                Ok((saved, results)) => ReportBaseline {
+                    label: match saved.timestamp {
+                        Some(timestamp) => format!(
+                            "{} ({})",
+                            path.display(),
+                            timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+                        ),
+                        None => path.display().to_string(),
+                    },
+                    regions: results.regions,
+                },
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
                 }
-            } else {
-                println!(
-                    "{}",
-                    serde_json::to_string_pretty(&parsed).unwrap_or(body_text)
-                );
-            }
+            });
+    apply_publish_options(
+        &mut request,
+        &cmd.request,
+        &cmd.publish,
+        &cmd.scope,
+        cmd.no_video,
+    );
+    if cmd.save_video.is_some() && cmd.publish.is_empty() && cmd.scope != "public" {
+        eprintln!(
+            "Error: videos are only rendered for published measurements, use --scope public or --publish with --save-video."
+        );
+        std::process::exit(1);
+    }
 
-            println!();
-            println!(
-                "================================================================================="
-            );
+    let message = format!("Measuring {} {}", request.method, request.url);
+    let response = match with_progress(&message, || execute_request(&cmd.request, &request)) {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            let run = CiRun {
+                name: format!("{} {url}", request.method),
+                outcome: Err(&e),
+            };
+            report_to_ci_or_exit(&cmd.ci, &[run]);
+            std::process::exit(error_exit_code(&e));
+        }
+    };
+
+    let breakdown = cmd.breakdown.then(|| {
+        let iterations = cmd.request.client.iterations.unwrap_or(LOCAL_ITERATIONS);
+        let message = format!("Timing the phases of {} {}", request.method, request.url);
+        let result = with_progress(&message, || {
+            measure_breakdown(&request, iterations, cmd.request.client.timeout)
+        });
+        result.unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(error_exit_code(&e));
+        })
+    });
 
-            let mut has_extra_data = false;
+    let share_url = response.url.clone().filter(|_| cmd.scope == "public");
+    let video_url = response.video_url.clone();
+    let request_hash = request.hash();
+    let regions = parse_regions(&response);
 
-            if let Some(url) = parsed["url"].as_str()
-                && cmd.scope == "public"
-            {
-                println!("🏎️  Share the results: {url}");
-                has_extra_data = true;
-            }
+    if !cmd.no_history {
+        let mut record = HistoryRecord::new(&request.method, &url, &request_hash, response);
+        if let Err(e) = save_history_record(&mut record) {
+            eprintln!("Warning: failed to save run to history: {e}");
+        }
+    }
 
-            if let Some(video_url) = parsed["videoUrl"].as_str() {
-                println!("🎬 Share the video: {}", video_url);
-                has_extra_data = true;
-            }
+    let report = MsReport {
+        method: request.method,
+        url,
+        request_hash,
+        body_source: request.body_source.map(|source| source.to_string()),
+        regions,
+        share_url,
+        video_url,
+        breakdown,
+    };
+
+    print_report(&report, cmd.output, cmd.verbose);
+
+    if let Some(path) = &cmd.report {
+        let headers = redact_headers(&request.params.measure_request.headers);
+        if let Err(e) = write_html_report(path, &report, &headers, report_baseline.as_ref()) {
+            eprintln!(
+                "Error: failed to write the report to '{}': {e}",
+                path.display()
+            );
+            std::process::exit(1);
+        }
+        eprintln!("Report saved to {}", path.display());
+    }
 
-            if has_extra_data {
-                println!(
-                    "================================================================================="
-                );
-            }
+    if let Some(path) = &cmd.save_video {
+        let Some(video_url) = &report.video_url else {
+            eprintln!("Error: no video was generated for this measurement.");
+            std::process::exit(1);
+        };
+        let client = cmd.request.client.ms_client();
+        save_video_or_exit(&client, video_url, path, cmd.video_timeout);
+    }
 
-            println!("❤️  By Borrow.dev \u{21C0} Open-Source Tools for Web Developers");
-            println!();
-        }
-        Err(e) => {
-            eprintln!("Error: request failed: {}", e);
+    let run = ExportedRun {
+        scenario: None,
+        method: &report.method,
+        url: &report.url,
+        regions: &report.regions,
+    };
+    export_or_exit(&cmd.export, &[run]);
+
+    if let Some(path) = &cmd.baseline.save_baseline {
+        if let Err(e) = save_baseline(path, &report) {
+            eprintln!("Error: {e}");
             std::process::exit(1);
         }
+        eprintln!("Baseline saved to {}", path.display());
+    }
+    let comparisons = baseline
+        .as_ref()
+        .map(|baseline| check_baseline(baseline, &report, cmd.baseline.tolerance))
+        .unwrap_or_default();
+    let regressed = comparisons
+        .iter()
+        .any(|c| matches!(c.outcome, BaselineOutcome::Regressed(_)));
+
+    let violations = cmd.thresholds.evaluate(&report.regions);
+    let run = CiRun {
+        name: format!("{} {}", report.method, report.url),
+        outcome: Ok(ci_regions(&report.regions, &violations, &comparisons)),
+    };
+    report_to_ci_or_exit(&cmd.ci, &[run]);
+
+    let failed_assertions = check_assertions(&report.regions);
+    enforce_thresholds(&cmd.thresholds, &report.regions);
+    if regressed || failed_assertions {
+        std::process::exit(THRESHOLD_EXIT_CODE);
     }
 }
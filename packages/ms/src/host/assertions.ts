@@ -0,0 +1,93 @@
+import type z from "zod";
+import type { msAssertionsSchema } from "./validation";
+
+export type MsAssertions = z.infer<typeof msAssertionsSchema>;
+
+type JsonValue = string | number | boolean | null | JsonValue[] | { [key: string]: JsonValue };
+
+/**
+ * Reads a JSON path such as `$.data.items[0].id` or `$["content-type"]`.
+ */
+function readJsonPath(root: JsonValue, path: string): { found: boolean; value?: JsonValue } {
+  const segment = /\.([A-Za-z_$][\w$]*)|\[(\d+)\]|\[(?:"([^"]*)"|'([^']*)')\]/y;
+  let value: JsonValue = root;
+  segment.lastIndex = 1;
+  while (segment.lastIndex < path.length) {
+    const match = segment.exec(path);
+    if (!match) {
+      return { found: false };
+    }
+    const key = match[1] ?? match[3] ?? match[4];
+    if (key !== undefined) {
+      if (value === null || typeof value !== "object" || Array.isArray(value) || !(key in value)) {
+        return { found: false };
+      }
+      value = value[key]!;
+    } else {
+      const index = Number(match[2]);
+      if (!Array.isArray(value) || index >= value.length) {
+        return { found: false };
+      }
+      value = value[index]!;
+    }
+  }
+  return { found: true, value };
+}
+
+/**
+ * Checks a response against the assertions, returning why it failed or `null` when it passed.
+ * Without a status assertion, any 2xx status is expected.
+ */
+export function checkAssertions(
+  response: { status: number; headers: Headers; text: string },
+  assertions: MsAssertions,
+): string | null {
+  const statuses = assertions.status;
+  const expectedStatus = statuses
+    ? statuses.includes(response.status)
+    : response.status >= 200 && response.status <= 299;
+  if (!expectedStatus) {
+    return `status ${response.status} is not ${statuses ? statuses.join(" or ") : "2xx"}`;
+  }
+
+  for (const { name, value } of assertions.headers ?? []) {
+    const actual = response.headers.get(name);
+    if (actual === null) {
+      return `header ${name} is missing`;
+    }
+    if (value !== undefined && !actual.includes(value)) {
+      return `header ${name} is "${actual}", expected it to contain "${value}"`;
+    }
+  }
+
+  for (const text of assertions.bodyContains ?? []) {
+    if (!response.text.includes(text)) {
+      return `body doesn't contain "${text}"`;
+    }
+  }
+
+  if (assertions.jsonPath?.length) {
+    let body: JsonValue;
+    try {
+      body = JSON.parse(response.text) as JsonValue;
+    } catch {
+      return "body isn't valid JSON";
+    }
+    for (const { path, operator, value } of assertions.jsonPath) {
+      const result = readJsonPath(body, path);
+      if (!operator) {
+        if (!result.found) {
+          return `${path} doesn't exist`;
+        }
+        continue;
+      }
+      const equal = result.found && JSON.stringify(result.value) === JSON.stringify(value ?? null);
+      if (equal !== (operator === "==")) {
+        const actual = result.found ? JSON.stringify(result.value) : "missing";
+        return `${path} is ${actual}, expected ${operator} ${JSON.stringify(value ?? null)}`;
+      }
+    }
+  }
+
+  return null;
+}
@@ -0,0 +1,9 @@
+/**
+ * Iterations per region when the request doesn't set them and `ITERATIONS` isn't configured.
+ */
+export const DEFAULT_ITERATIONS = 10;
+
+/**
+ * Upper bound for the iterations per region, each iteration is a subrequest of the worker.
+ */
+export const MAX_ITERATIONS = 50;
@@ -2,9 +2,18 @@ import z from "zod";
 import { msParamsSchema } from "./validation";
 import { env } from "cloudflare:workers";
 import workersPlacementRegions from "@borrowdev/data/cloudflare-workers-placement-regions";
+import { DEFAULT_ITERATIONS } from "./constants";
 
 type RegionKey = keyof typeof workersPlacementRegions;
-type MeasureResult = { amount: number; p50: number; p90: number; p99: number };
+type MeasureResult = {
+  amount: number;
+  iterations: number;
+  assertionFailures?: number;
+  firstAssertionFailure?: string;
+  p50: number;
+  p90: number;
+  p99: number;
+};
 type EnhancedMeasureResult = {
   metadata: (typeof workersPlacementRegions)[RegionKey];
   data: MeasureResult;
@@ -49,7 +58,8 @@ async function ms(params: z.infer<typeof msParamsSchema>): Promise<MsResult> {
           method: measureRequest.method,
           headers: measureRequest.headers,
           body: "body" in measureRequest ? measureRequest.body : undefined,
-          iterations: env.ITERATIONS || 10,
+          iterations: data.req.iterations ?? (env.ITERATIONS || DEFAULT_ITERATIONS),
+          assertions: data.req.assertions,
         }),
       });
 
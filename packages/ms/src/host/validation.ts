@@ -1,5 +1,6 @@
 import { z } from "zod";
 import workersPlacementRegions from "@borrowdev/data/cloudflare-workers-placement-regions";
+import { MAX_ITERATIONS } from "./constants";
 
 const msParamsMeasureRequestCommonSchema = z.object({
   method: z.union([z.literal("GET"), z.literal("POST"), z.literal("PUT"), z.literal("DELETE")]),
@@ -13,6 +14,35 @@ const regionsUnion = z.union(
   ),
 );
 
+const msAssertionsSchema = z.object({
+  /**
+   * Accepted response statuses, any 2xx status when not set.
+   */
+  status: z.array(z.number().int().min(100).max(599)).min(1).optional(),
+  /**
+   * Headers the response must have, with a text their value must contain.
+   */
+  headers: z
+    .array(z.object({ name: z.string().min(1), value: z.string().optional() }))
+    .optional(),
+  /**
+   * Texts the response body must contain.
+   */
+  bodyContains: z.array(z.string()).optional(),
+  /**
+   * JSON paths of the response body, e.g. `$.ok`, that must exist or compare to a value.
+   */
+  jsonPath: z
+    .array(
+      z.object({
+        path: z.string().startsWith("$"),
+        operator: z.union([z.literal("=="), z.literal("!=")]).optional(),
+        value: z.union([z.string(), z.number(), z.boolean(), z.null()]).optional(),
+      }),
+    )
+    .optional(),
+});
+
 const msParamsSchema = z.object({
   /**
    * An array of regions to measure against. If null, will measure against all regions.
@@ -25,6 +55,14 @@ const msParamsSchema = z.object({
 
   req: z.object({
     action: z.literal("measure"),
+    /**
+     * The amount of requests made to the API from each region, defaults to the `ITERATIONS` variable.
+     */
+    iterations: z.number().int().min(1).max(MAX_ITERATIONS).optional(),
+    /**
+     * Checks every response must pass to count as successful, instead of any 2xx status.
+     */
+    assertions: msAssertionsSchema.optional(),
     measureRequest: z.discriminatedUnion("method", [
       msParamsMeasureRequestCommonSchema.extend({
         method: z.literal("GET"),
@@ -53,6 +91,18 @@ const msResultSuccessSchema = z.object({
          * The amount of requests made to the API
          */
         amount: z.number(),
+        /**
+         * The amount of requests that were attempted
+         */
+        iterations: z.number(),
+        /**
+         * The amount of responses that failed the assertions, when the request has any
+         */
+        assertionFailures: z.number().optional(),
+        /**
+         * Why the first of these responses failed
+         */
+        firstAssertionFailure: z.string().optional(),
         /**
          * The 50th percentile latency in milliseconds
          */
@@ -70,4 +120,4 @@ const msResultSuccessSchema = z.object({
   ),
 });
 
-export { msParamsSchema, msResultSuccessSchema };
+export { msAssertionsSchema, msParamsSchema, msResultSuccessSchema };
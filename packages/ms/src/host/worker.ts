@@ -1,4 +1,6 @@
 import { env } from "cloudflare:workers";
+import { checkAssertions, type MsAssertions } from "./assertions";
+import { DEFAULT_ITERATIONS, MAX_ITERATIONS } from "./constants";
 
 export default {
   async fetch(request: Request): Promise<Response> {
@@ -6,24 +8,34 @@ export default {
       return new Response("Method not allowed", { status: 405 });
     }
 
-    const { url, method, headers, body, iterations, invokeSecret } = (await request.json()) as {
-      invokeSecret: string;
-      url: string;
-      method: string;
-      headers?: Record<string, string>;
-      body?: string;
-      iterations: number;
-    };
+    const { url, method, headers, body, iterations, assertions, invokeSecret } =
+      (await request.json()) as {
+        invokeSecret: string;
+        url: string;
+        method: string;
+        headers?: Record<string, string>;
+        body?: string;
+        iterations: number;
+        assertions?: MsAssertions;
+      };
 
     if (invokeSecret !== env.MS_INVOKE_SECRET) {
       return new Response("Unauthorized", { status: 401 });
     }
 
+    // The host validates the iterations, but the worker must never exceed its subrequest limit.
+    const count = Math.min(
+      Math.max(1, Math.floor(Number(iterations) || DEFAULT_ITERATIONS)),
+      MAX_ITERATIONS,
+    );
+
     const latencies: number[] = [];
     let amount = 0;
+    let assertionFailures = 0;
+    let firstAssertionFailure: string | undefined;
 
     const responses = [];
-    for (let i = 0; i < iterations; i++) {
+    for (let i = 0; i < count; i++) {
       const start = performance.now();
       try {
         const res = await fetch(url, {
@@ -31,10 +43,24 @@ export default {
           headers,
           body,
         });
-        if (res.ok) {
-          amount++;
+        const text = await res.text();
+        if (!assertions) {
+          if (res.ok) {
+            amount++;
+          }
+        } else {
+          const failure = checkAssertions(
+            { status: res.status, headers: res.headers, text },
+            assertions,
+          );
+          if (failure === null) {
+            amount++;
+          } else {
+            assertionFailures++;
+            firstAssertionFailure ??= failure;
+          }
         }
-        responses.push({ text: await res.text(), status: res.status });
+        responses.push({ text, status: res.status });
       } catch (err) {
         responses.push({ text: (err as Error).message, status: -1 });
         // request failed, don't count as successful
@@ -50,7 +76,8 @@ export default {
       return Math.round(latencies[Math.max(0, idx)]! * 100) / 100;
     };
 
-    if (amount === 0) {
+    // Responses that failed the assertions are reported, not treated as an unreachable API.
+    if (amount === 0 && assertionFailures === 0) {
       return new Response(
         `Upstream error: ${responses.map((r) => `${r.status} ${r.text}`).join(", ")}`,
         { status: 400 },
@@ -59,6 +86,8 @@ export default {
 
     return Response.json({
       amount,
+      iterations: count,
+      ...(assertions && { assertionFailures, firstAssertionFailure }),
       p50: percentile(50),
       p90: percentile(90),
       p99: percentile(99),
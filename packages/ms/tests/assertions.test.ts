@@ -0,0 +1,91 @@
+import { checkAssertions } from "@/host/assertions";
+import { describe, expect, test } from "vitest";
+
+const response = (status: number, body: unknown, headers: Record<string, string> = {}) => ({
+  status,
+  headers: new Headers(headers),
+  text: typeof body === "string" ? body : JSON.stringify(body),
+});
+
+describe("checkAssertions - status", () => {
+  test("should expect a 2xx status by default", () => {
+    expect(checkAssertions(response(204, ""), {})).toBeNull();
+    expect(checkAssertions(response(404, ""), {})).toBe("status 404 is not 2xx");
+  });
+
+  test("should replace the 2xx check with the given statuses", () => {
+    expect(checkAssertions(response(404, ""), { status: [404] })).toBeNull();
+    expect(checkAssertions(response(200, ""), { status: [201, 202] })).toBe(
+      "status 200 is not 201 or 202",
+    );
+  });
+});
+
+describe("checkAssertions - headers and body", () => {
+  test("should check that headers exist and contain a value", () => {
+    const ok = response(200, "", { "Content-Type": "application/json; charset=utf-8" });
+    expect(checkAssertions(ok, { headers: [{ name: "content-type" }] })).toBeNull();
+    expect(
+      checkAssertions(ok, { headers: [{ name: "Content-Type", value: "application/json" }] }),
+    ).toBeNull();
+    expect(checkAssertions(ok, { headers: [{ name: "Content-Type", value: "text/html" }] })).toBe(
+      'header Content-Type is "application/json; charset=utf-8", expected it to contain "text/html"',
+    );
+    expect(checkAssertions(ok, { headers: [{ name: "X-Request-Id" }] })).toBe(
+      "header X-Request-Id is missing",
+    );
+  });
+
+  test("should check that the body contains every text", () => {
+    const ok = response(200, "hello world");
+    expect(checkAssertions(ok, { bodyContains: ["hello", "world"] })).toBeNull();
+    expect(checkAssertions(ok, { bodyContains: ["hello", "there"] })).toBe(
+      `body doesn't contain "there"`,
+    );
+  });
+});
+
+describe("checkAssertions - JSON paths", () => {
+  const body = {
+    ok: true,
+    data: { items: [{ id: 7 }], "a b": "spaced", "content-type": null },
+  };
+
+  test("should read dotted, indexed and quoted paths", () => {
+    for (const path of [
+      "$",
+      "$.ok",
+      "$.data.items[0].id",
+      '$.data["a b"]',
+      "$.data['content-type']",
+    ]) {
+      expect(checkAssertions(response(200, body), { jsonPath: [{ path }] }), path).toBeNull();
+    }
+  });
+
+  test("should report missing paths", () => {
+    for (const path of ["$.missing", "$.data.items[1]", "$.ok.nested", "$.data.items.id", "$[x]"]) {
+      expect(checkAssertions(response(200, body), { jsonPath: [{ path }] })).toBe(
+        `${path} doesn't exist`,
+      );
+    }
+  });
+
+  test("should compare values with == and !=", () => {
+    const check = (path: string, operator: "==" | "!=", value: string | number | boolean | null) =>
+      checkAssertions(response(200, body), { jsonPath: [{ path, operator, value }] });
+    expect(check("$.data.items[0].id", "==", 7)).toBeNull();
+    expect(check('$.data["a b"]', "==", "spaced")).toBeNull();
+    expect(check("$.data['content-type']", "==", null)).toBeNull();
+    expect(check("$.ok", "!=", false)).toBeNull();
+    expect(check("$.ok", "==", "true")).toBe('$.ok is true, expected == "true"');
+    expect(check("$.missing", "!=", 1)).toBeNull();
+    expect(check("$.missing", "==", 1)).toBe("$.missing is missing, expected == 1");
+  });
+
+  test("should fail when the body isn't JSON", () => {
+    expect(checkAssertions(response(200, "<html>"), { jsonPath: [{ path: "$.ok" }] })).toBe(
+      "body isn't valid JSON",
+    );
+  });
+});